@@ -1,6 +1,39 @@
+extern crate sha2;
+
 use std::env;
 use std::path::{Path, PathBuf};
 
+#[path = "src/known_files.rs"]
+mod known_files;
+
+use known_files::{VerifyError, KNOWN_LIBRARIES};
+
+#[cfg(target_os = "linux")]
+const LIB_PATH: &str = "res/linux/libdeepspeech.so";
+
+#[cfg(target_os = "windows")]
+const LIB_PATH: &str = "res/windows/libdeepspeech.so";
+
+/// Checks that the bundled library is not a Git LFS pointer or otherwise not
+/// the expected file. Left unchecked this shows up as a confusing linker or
+/// loader error
+fn verify_lib(path: &Path) -> Result<(), String> {
+    match known_files::verify(path, KNOWN_LIBRARIES) {
+        Ok(_) => Ok(()),
+        Err(VerifyError::Read(e)) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        Err(VerifyError::LfsPointer) => Err(format!(
+            "{} is a Git LFS pointer, not the library itself. Install git-lfs and run \
+             `git lfs pull` to fetch it",
+            path.display()
+        )),
+        Err(e) => Err(format!(
+            "{} has {}. Run `git lfs pull` to fetch it again",
+            path.display(),
+            e
+        )),
+    }
+}
+
 fn copy_file(src: &Path, dst: &Path) {
     if dst.exists() {
         std::fs::remove_file(&dst).unwrap();
@@ -12,7 +45,7 @@ fn copy_file(src: &Path, dst: &Path) {
 #[cfg(target_os = "linux")]
 fn copy_lib_to_out_dir(manifest_dir: &Path, out_dir: &Path) {
     copy_file(
-        &manifest_dir.join("res/linux/libdeepspeech.so"),
+        &manifest_dir.join(LIB_PATH),
         &out_dir.join("libdeepspeech.so"),
     );
 }
@@ -25,10 +58,7 @@ fn copy_lib_to_out_dir(manifest_dir: &Path, out_dir: &Path) {
     // renamed dll.
     let output_so = out_dir.join("libdeepspeech.so");
     let output_dll = out_dir.join("libdeepspeech.dll");
-    copy_file(
-        &manifest_dir.join("res/windows/libdeepspeech.so"),
-        &output_so,
-    );
+    copy_file(&manifest_dir.join(LIB_PATH), &output_so);
     if output_dll.exists() {
         std::fs::remove_file(&output_dll).unwrap();
     }
@@ -39,20 +69,34 @@ fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
 
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
-    println!("cargo:rustc-link-lib=dylib=deepspeech");
+    let runtime_load = env::var_os("CARGO_FEATURE_RUNTIME_LOAD").is_some();
 
-    let bindings = bindgen::builder()
-        .header(&manifest_dir.join("res/deepspeech.h").display().to_string())
-        .generate()
-        .unwrap();
+    let mut builder =
+        bindgen::builder().header(&manifest_dir.join("res/deepspeech.h").display().to_string());
+
+    if runtime_load {
+        // Functions are provided by src/dynamic.rs instead
+        builder = builder.blocklist_function("DS_.*");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=deepspeech");
+    }
+
+    let bindings = builder.generate().unwrap();
 
     bindings
         .write_to_file(format!("{}/bindings.rs", out_dir))
         .unwrap();
 
-    // Cargo will append link search paths to LD_LIBRARY_PATH/PATH, but only if
-    // they're in OUT_DIR. Copy the lib there so that cargo run works with no
-    // configuration
-    copy_lib_to_out_dir(&manifest_dir, &PathBuf::from(&out_dir));
-    println!("cargo:rustc-link-search={}", out_dir);
+    match verify_lib(&manifest_dir.join(LIB_PATH)) {
+        // Cargo will append link search paths to LD_LIBRARY_PATH/PATH, but
+        // only if they're in OUT_DIR. Copy the lib there so that cargo run
+        // works with no configuration
+        Ok(()) => {
+            copy_lib_to_out_dir(&manifest_dir, &PathBuf::from(&out_dir));
+            println!("cargo:rustc-link-search={}", out_dir);
+        }
+        // Not fatal, the library can still be found at runtime
+        Err(e) if runtime_load => println!("cargo:warning={}", e),
+        Err(e) => panic!("{}", e),
+    }
 }
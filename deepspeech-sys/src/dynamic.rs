@@ -0,0 +1,149 @@
+//! DS_* functions resolved from a libdeepspeech loaded at runtime with
+//! [`load_library`]. Calling any of them before the library is loaded panics
+
+use libloading::Library;
+use once_cell::sync::OnceCell;
+
+use std::ffi::{CStr, OsStr};
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_short, c_uint};
+
+use {Metadata, ModelState, StreamingState};
+
+/// Major and minor version of the DeepSpeech API the bindings were generated
+/// for
+const SUPPORTED_VERSION: &str = "0.9.";
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The library could not be opened
+    Open(libloading::Error),
+    /// The library was opened but does not export the DeepSpeech API
+    MissingSymbol(libloading::Error),
+    /// The library is a DeepSpeech release with a different API
+    Version(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Open(ref e) => write!(f, "{}", e),
+            LoadError::MissingSymbol(ref e) => write!(f, "not a DeepSpeech library: {}", e),
+            LoadError::Version(ref v) => write!(
+                f,
+                "DeepSpeech {} is not supported, expected {}x",
+                v, SUPPORTED_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+macro_rules! dynamic_api {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)*;)*) => {
+        struct Api {
+            // Keeps the functions below valid
+            _library: Library,
+            $($name: unsafe extern "C" fn($($ty),*) $(-> $ret)*,)*
+        }
+
+        impl Api {
+            unsafe fn load(library: Library) -> Result<Api, libloading::Error> {
+                $(
+                    let $name = *library.get::<unsafe extern "C" fn($($ty),*) $(-> $ret)*>(
+                        concat!(stringify!($name), "\0").as_bytes(),
+                    )?;
+                )*
+
+                Ok(Api {
+                    _library: library,
+                    $($name,)*
+                })
+            }
+        }
+
+        $(
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)* {
+                (api().$name)($($arg),*)
+            }
+        )*
+    };
+}
+
+dynamic_api! {
+    fn DS_CreateModel(aModelPath: *const c_char, retval: *mut *mut ModelState) -> c_int;
+    fn DS_GetModelBeamWidth(aCtx: *const ModelState) -> c_uint;
+    fn DS_SetModelBeamWidth(aCtx: *mut ModelState, aBeamWidth: c_uint) -> c_int;
+    fn DS_GetModelSampleRate(aCtx: *const ModelState) -> c_int;
+    fn DS_FreeModel(ctx: *mut ModelState);
+    fn DS_EnableExternalScorer(aCtx: *mut ModelState, aScorerPath: *const c_char) -> c_int;
+    fn DS_AddHotWord(aCtx: *mut ModelState, word: *const c_char, boost: f32) -> c_int;
+    fn DS_EraseHotWord(aCtx: *mut ModelState, word: *const c_char) -> c_int;
+    fn DS_ClearHotWords(aCtx: *mut ModelState) -> c_int;
+    fn DS_DisableExternalScorer(aCtx: *mut ModelState) -> c_int;
+    fn DS_SetScorerAlphaBeta(aCtx: *mut ModelState, aAlpha: f32, aBeta: f32) -> c_int;
+    fn DS_SpeechToText(
+        aCtx: *mut ModelState,
+        aBuffer: *const c_short,
+        aBufferSize: c_uint
+    ) -> *mut c_char;
+    fn DS_SpeechToTextWithMetadata(
+        aCtx: *mut ModelState,
+        aBuffer: *const c_short,
+        aBufferSize: c_uint,
+        aNumResults: c_uint
+    ) -> *mut Metadata;
+    fn DS_CreateStream(aCtx: *mut ModelState, retval: *mut *mut StreamingState) -> c_int;
+    fn DS_FeedAudioContent(
+        aSctx: *mut StreamingState,
+        aBuffer: *const c_short,
+        aBufferSize: c_uint
+    );
+    fn DS_IntermediateDecode(aSctx: *const StreamingState) -> *mut c_char;
+    fn DS_IntermediateDecodeWithMetadata(
+        aSctx: *const StreamingState,
+        aNumResults: c_uint
+    ) -> *mut Metadata;
+    fn DS_FinishStream(aSctx: *mut StreamingState) -> *mut c_char;
+    fn DS_FinishStreamWithMetadata(
+        aSctx: *mut StreamingState,
+        aNumResults: c_uint
+    ) -> *mut Metadata;
+    fn DS_FreeStream(aSctx: *mut StreamingState);
+    fn DS_FreeMetadata(m: *mut Metadata);
+    fn DS_FreeString(str: *mut c_char);
+    fn DS_Version() -> *mut c_char;
+    fn DS_ErrorCodeToErrorMessage(aErrorCode: c_int) -> *mut c_char;
+}
+
+static API: OnceCell<Api> = OnceCell::new();
+
+fn api() -> &'static Api {
+    API.get()
+        .expect("libdeepspeech used before load_library succeeded")
+}
+
+/// Opens the DeepSpeech library at path, which is searched for like
+/// [`Library::new`] does. Once a library is loaded later calls do nothing
+pub fn load_library<P: AsRef<OsStr>>(path: P) -> Result<(), LoadError> {
+    API.get_or_try_init(|| unsafe {
+        let library = Library::new(path).map_err(LoadError::Open)?;
+        let api = Api::load(library).map_err(LoadError::MissingSymbol)?;
+
+        let version_ptr = (api.DS_Version)();
+        let version = CStr::from_ptr(version_ptr).to_string_lossy().into_owned();
+        (api.DS_FreeString)(version_ptr);
+
+        if !version.starts_with(SUPPORTED_VERSION) {
+            return Err(LoadError::Version(version));
+        }
+
+        Ok(api)
+    })
+    .map(|_| ())
+}
+
+pub fn is_loaded() -> bool {
+    API.get().is_some()
+}
@@ -0,0 +1,117 @@
+//! Checks that a file is the real one and not a Git LFS pointer or a corrupt
+//! download, along with the size and checksum of the bundled libdeepspeech.
+//! Also compiled into build.rs, so that the build and the runtime loader check
+//! against the same values
+
+use sha2::{Digest, Sha256};
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// First line of every Git LFS pointer file
+const LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+/// Pointer files are tiny, anything larger is assumed to be real content
+const MAX_LFS_POINTER_SIZE: u64 = 1024;
+
+pub struct KnownFile {
+    pub name: &'static str,
+    pub size: u64,
+    pub sha256: &'static str,
+}
+
+/// The library in res/ for this platform
+#[cfg(target_os = "linux")]
+pub const KNOWN_LIBRARIES: &[KnownFile] = &[KnownFile {
+    name: "libdeepspeech.so",
+    size: 4271240,
+    sha256: "6d0178ced7c690afd07d8a8b6e7e39fa85767e23fb3bd2156a28292d05bad3e9",
+}];
+#[cfg(target_os = "windows")]
+pub const KNOWN_LIBRARIES: &[KnownFile] = &[KnownFile {
+    name: "libdeepspeech.so",
+    size: 2492928,
+    sha256: "6443d780be428ca731ac0067d1e1de4116558f2827cd70b18377e9b7ce136772",
+}];
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub const KNOWN_LIBRARIES: &[KnownFile] = &[];
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Read(io::Error),
+    LfsPointer,
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    ChecksumMismatch {
+        expected: &'static str,
+        actual: String,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerifyError::Read(ref err) => write!(f, "{}", err),
+            VerifyError::LfsPointer => write!(f, "Git LFS pointer instead of the file itself"),
+            VerifyError::SizeMismatch { expected, actual } => {
+                write!(f, "{} bytes, expected {}", actual, expected)
+            }
+            VerifyError::ChecksumMismatch {
+                expected,
+                ref actual,
+            } => write!(f, "checksum {}, expected {}", actual, expected),
+        }
+    }
+}
+
+/// Checks that path is not a Git LFS pointer, and that files in known match
+/// their size and checksum. Returns the size and checksum of the file
+pub fn verify(path: &Path, known: &[KnownFile]) -> Result<(u64, String), VerifyError> {
+    let file_size = std::fs::metadata(path).map_err(VerifyError::Read)?.len();
+    if file_size <= MAX_LFS_POINTER_SIZE && is_lfs_pointer(path).map_err(VerifyError::Read)? {
+        return Err(VerifyError::LfsPointer);
+    }
+
+    let known = path
+        .file_name()
+        .and_then(|name| known.iter().find(|k| name == k.name));
+
+    if let Some(known) = known {
+        if file_size != known.size {
+            return Err(VerifyError::SizeMismatch {
+                expected: known.size,
+                actual: file_size,
+            });
+        }
+    }
+
+    let sha256 = sha256(path).map_err(VerifyError::Read)?;
+    if let Some(known) = known {
+        if sha256 != known.sha256 {
+            return Err(VerifyError::ChecksumMismatch {
+                expected: known.sha256,
+                actual: sha256,
+            });
+        }
+    }
+
+    Ok((file_size, sha256))
+}
+
+fn is_lfs_pointer(path: &Path) -> io::Result<bool> {
+    let mut content = Vec::new();
+    File::open(path)?
+        .take(LFS_POINTER_PREFIX.len() as u64)
+        .read_to_end(&mut content)?;
+    Ok(content == LFS_POINTER_PREFIX)
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
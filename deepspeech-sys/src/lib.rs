@@ -3,4 +3,18 @@
 #![allow(non_snake_case)]
 #![allow(dead_code)]
 
+extern crate sha2;
+
+#[cfg(feature = "runtime-load")]
+extern crate libloading;
+#[cfg(feature = "runtime-load")]
+extern crate once_cell;
+
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+pub mod known_files;
+
+#[cfg(feature = "runtime-load")]
+mod dynamic;
+#[cfg(feature = "runtime-load")]
+pub use dynamic::*;
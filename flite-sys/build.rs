@@ -1,9 +1,11 @@
 use std::env;
+use std::fmt::Write;
 use std::path::PathBuf;
 
 // Subset of files to give us the ability to call flite_text_to_wave and
-// cst_wave_resample
-const FILE_LIST: [&str; 144] = [
+// cst_wave_resample. Languages and voices are added on top of this depending
+// on which features are enabled
+const CORE_FILES: [&str; 66] = [
     "src/audio/auclient.c",
     "src/audio/auserver.c",
     "src/audio/audio.c",
@@ -70,15 +72,15 @@ const FILE_LIST: [&str; 144] = [
     "src/cg/cst_cg_dump_voice.c",
     "src/cg/cst_cg_map.c",
     "src/cg/cst_spamf0.c",
+];
+
+const USENGLISH_FILES: [&str; 22] = [
     "lang/cmulex/cmu_lts_rules.c",
     "lang/cmulex/cmu_lts_model.c",
     "lang/cmulex/cmu_lex.c",
     "lang/cmulex/cmu_lex_entries.c",
     "lang/cmulex/cmu_lex_data.c",
     "lang/cmulex/cmu_postlex.c",
-    "lang/cmu_indic_lex/cmu_indic_lex.c",
-    "lang/cmu_grapheme_lex/cmu_grapheme_lex.c",
-    "lang/cmu_grapheme_lex/grapheme_unitran_tables.c",
     "lang/usenglish/us_int_accent_cart.c",
     "lang/usenglish/us_int_tone_cart.c",
     "lang/usenglish/us_f0_model.c",
@@ -95,29 +97,41 @@ const FILE_LIST: [&str; 144] = [
     "lang/usenglish/us_aswd.c",
     "lang/usenglish/usenglish.c",
     "lang/usenglish/us_pos_cart.c",
+];
+
+const INDIC_FILES: [&str; 4] = [
+    "lang/cmu_indic_lex/cmu_indic_lex.c",
     "lang/cmu_indic_lang/cmu_indic_lang.c",
     "lang/cmu_indic_lang/cmu_indic_phoneset.c",
     "lang/cmu_indic_lang/cmu_indic_phrasing_cart.c",
+];
+
+const GRAPHEME_FILES: [&str; 5] = [
+    "lang/cmu_grapheme_lex/cmu_grapheme_lex.c",
+    "lang/cmu_grapheme_lex/grapheme_unitran_tables.c",
     "lang/cmu_grapheme_lang/cmu_grapheme_lang.c",
     "lang/cmu_grapheme_lang/cmu_grapheme_phoneset.c",
     "lang/cmu_grapheme_lang/cmu_grapheme_phrasing_cart.c",
+];
+
+const CMU_US_KAL_FILES: [&str; 6] = [
     "lang/cmu_us_kal/cmu_us_kal_diphone.c",
     "lang/cmu_us_kal/cmu_us_kal.c",
     "lang/cmu_us_kal/cmu_us_kal_lpc.c",
     "lang/cmu_us_kal/cmu_us_kal_res.c",
     "lang/cmu_us_kal/cmu_us_kal_residx.c",
     "lang/cmu_us_kal/cmu_us_kal_ressize.c",
-    "lang/cmu_time_awb/cmu_time_awb.c",
-    "lang/cmu_time_awb/cmu_time_awb_clunits.c",
-    "lang/cmu_time_awb/cmu_time_awb_cart.c",
-    "lang/cmu_time_awb/cmu_time_awb_mcep.c",
-    "lang/cmu_time_awb/cmu_time_awb_lpc.c",
-    "lang/cmu_time_awb/cmu_time_awb_lex_entry.c",
+];
+
+const CMU_US_KAL16_FILES: [&str; 5] = [
     "lang/cmu_us_kal16/cmu_us_kal16_diphone.c",
     "lang/cmu_us_kal16/cmu_us_kal16.c",
     "lang/cmu_us_kal16/cmu_us_kal16_lpc.c",
     "lang/cmu_us_kal16/cmu_us_kal16_res.c",
     "lang/cmu_us_kal16/cmu_us_kal16_residx.c",
+];
+
+const CMU_US_AWB_FILES: [&str; 10] = [
     "lang/cmu_us_awb/cmu_us_awb.c",
     "lang/cmu_us_awb/cmu_us_awb_cg_single_mcep_trees.c",
     "lang/cmu_us_awb/cmu_us_awb_cg.c",
@@ -128,6 +142,9 @@ const FILE_LIST: [&str; 144] = [
     "lang/cmu_us_awb/cmu_us_awb_spamf0_accent_params.c",
     "lang/cmu_us_awb/cmu_us_awb_spamf0_phrase.c",
     "lang/cmu_us_awb/cmu_us_awb_spamf0_accent.c",
+];
+
+const CMU_US_RMS_FILES: [&str; 10] = [
     "lang/cmu_us_rms/cmu_us_rms.c",
     "lang/cmu_us_rms/cmu_us_rms_cg_single_mcep_trees.c",
     "lang/cmu_us_rms/cmu_us_rms_cg.c",
@@ -138,6 +155,9 @@ const FILE_LIST: [&str; 144] = [
     "lang/cmu_us_rms/cmu_us_rms_spamf0_accent_params.c",
     "lang/cmu_us_rms/cmu_us_rms_spamf0_phrase.c",
     "lang/cmu_us_rms/cmu_us_rms_spamf0_accent.c",
+];
+
+const CMU_US_SLT_FILES: [&str; 10] = [
     "lang/cmu_us_slt/cmu_us_slt.c",
     "lang/cmu_us_slt/cmu_us_slt_cg_single_mcep_trees.c",
     "lang/cmu_us_slt/cmu_us_slt_cg.c",
@@ -150,20 +170,186 @@ const FILE_LIST: [&str; 144] = [
     "lang/cmu_us_slt/cmu_us_slt_spamf0_accent_params.c",
 ];
 
+const CMU_TIME_AWB_FILES: [&str; 6] = [
+    "lang/cmu_time_awb/cmu_time_awb.c",
+    "lang/cmu_time_awb/cmu_time_awb_clunits.c",
+    "lang/cmu_time_awb/cmu_time_awb_cart.c",
+    "lang/cmu_time_awb/cmu_time_awb_mcep.c",
+    "lang/cmu_time_awb/cmu_time_awb_lpc.c",
+    "lang/cmu_time_awb/cmu_time_awb_lex_entry.c",
+];
+
+struct Lang {
+    feature: &'static str,
+    files: &'static [&'static str],
+    /// (name, lang init function, lexicon init function) passed to
+    /// flite_add_lang
+    registrations: &'static [(&'static str, &'static str, &'static str)],
+}
+
+// No built in voice uses the indic or grapheme languages, they are only needed
+// by .flitevox voices loaded from a file
+const LANGS: [Lang; 3] = [
+    Lang {
+        feature: "usenglish",
+        files: &USENGLISH_FILES,
+        registrations: &[
+            ("eng", "usenglish_init", "cmulex_init"),
+            ("usenglish", "usenglish_init", "cmulex_init"),
+        ],
+    },
+    Lang {
+        feature: "cmu_indic_lang",
+        files: &INDIC_FILES,
+        registrations: &[(
+            "cmu_indic_lang",
+            "cmu_indic_lang_init",
+            "cmu_indic_lex_init",
+        )],
+    },
+    Lang {
+        feature: "cmu_grapheme_lang",
+        files: &GRAPHEME_FILES,
+        registrations: &[(
+            "cmu_grapheme_lang",
+            "cmu_grapheme_lang_init",
+            "cmu_grapheme_lex_init",
+        )],
+    },
+];
+
+struct Voice {
+    /// Both the feature name and the suffix of the register_ function
+    name: &'static str,
+    files: &'static [&'static str],
+}
+
+// Registration order is the order voices are listed in, the first one is the
+// default voice
+const VOICES: [Voice; 6] = [
+    Voice {
+        name: "cmu_us_kal",
+        files: &CMU_US_KAL_FILES,
+    },
+    Voice {
+        name: "cmu_us_kal16",
+        files: &CMU_US_KAL16_FILES,
+    },
+    Voice {
+        name: "cmu_us_awb",
+        files: &CMU_US_AWB_FILES,
+    },
+    Voice {
+        name: "cmu_us_rms",
+        files: &CMU_US_RMS_FILES,
+    },
+    Voice {
+        name: "cmu_us_slt",
+        files: &CMU_US_SLT_FILES,
+    },
+    Voice {
+        name: "cmu_time_awb",
+        files: &CMU_TIME_AWB_FILES,
+    },
+];
+
+fn feature_enabled(feature: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_some()
+}
+
+fn generate_lang_list(langs: &[&Lang]) -> String {
+    let mut ret = String::from("/* Generated by flite-sys/build.rs */\n\n#include \"flite.h\"\n\n");
+
+    let mut declared = Vec::new();
+    for &(_, lang_init, lex_init) in langs.iter().flat_map(|l| l.registrations) {
+        if !declared.contains(&lang_init) {
+            writeln!(ret, "void {}(cst_voice *v);", lang_init).unwrap();
+            declared.push(lang_init);
+        }
+        if !declared.contains(&lex_init) {
+            writeln!(ret, "cst_lexicon *{}(void);", lex_init).unwrap();
+            declared.push(lex_init);
+        }
+    }
+
+    ret.push_str("\nvoid flite_set_lang_list(void)\n{\n");
+    for (name, lang_init, lex_init) in langs.iter().flat_map(|l| l.registrations) {
+        writeln!(
+            ret,
+            "   flite_add_lang(\"{}\",{},{});",
+            name, lang_init, lex_init
+        )
+        .unwrap();
+    }
+    ret.push_str("}\n");
+
+    ret
+}
+
+fn generate_voice_list(voices: &[&Voice]) -> String {
+    let mut ret = String::from("/* Generated by flite-sys/build.rs */\n\n#include \"flite.h\"\n\n");
+
+    for voice in voices {
+        writeln!(
+            ret,
+            "cst_voice *register_{}(const char *voxdir);",
+            voice.name
+        )
+        .unwrap();
+    }
+
+    ret.push_str("\ncst_val *flite_set_voice_list(const char *voxdir)\n{\n");
+    for voice in voices {
+        writeln!(
+            ret,
+            "   flite_voice_list = cons_val(voice_val(register_{}(voxdir)),flite_voice_list);",
+            voice.name
+        )
+        .unwrap();
+    }
+    ret.push_str("   flite_voice_list = val_reverse(flite_voice_list);\n");
+    ret.push_str("   return flite_voice_list;\n}\n");
+
+    ret
+}
+
 fn main() {
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let vendor_path = manifest_dir.join("vendor");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let langs = LANGS
+        .iter()
+        .filter(|l| feature_enabled(l.feature))
+        .collect::<Vec<_>>();
+    let voices = VOICES
+        .iter()
+        .filter(|v| feature_enabled(v.name))
+        .collect::<Vec<_>>();
+    if voices.is_empty() {
+        panic!(
+            "No flite voice enabled, enable at least one of the features {}",
+            VOICES.iter().map(|v| v.name).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let lang_list_path = out_dir.join("flite_lang_list.c");
+    std::fs::write(&lang_list_path, generate_lang_list(&langs)).unwrap();
+    let voice_list_path = out_dir.join("flite_voice_list.c");
+    std::fs::write(&voice_list_path, generate_voice_list(&voices)).unwrap();
+
+    let files = CORE_FILES
+        .iter()
+        .chain(langs.iter().flat_map(|l| l.files))
+        .chain(voices.iter().flat_map(|v| v.files))
+        .map(|&p| vendor_path.join(p))
+        .collect::<Vec<_>>();
 
     let mut builder = cc::Build::new();
     builder
-        .files(
-            FILE_LIST
-                .iter()
-                .map(|&p| vendor_path.join(p))
-                .collect::<Vec<_>>(),
-        )
-        .file(manifest_dir.join("src/flite_voice_list.c"))
-        .file(manifest_dir.join("src/flite_lang_list.c"))
+        .files(files)
+        .file(voice_list_path)
+        .file(lang_list_path)
         .include(vendor_path.join("include"))
         .include(vendor_path.join("lang/usenglish"))
         .include(vendor_path.join("lang/cmulex"))
@@ -187,9 +373,8 @@ fn main() {
         .blocklist_item("_JUMP_BUFFER")
         .generate();
 
-    let out_dir = env::var("OUT_DIR").unwrap();
     bindings
         .unwrap()
-        .write_to_file(format!("{}/flite.rs", out_dir))
+        .write_to_file(out_dir.join("flite.rs"))
         .unwrap();
 }
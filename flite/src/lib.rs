@@ -1,4 +1,62 @@
-use std::ffi::{CStr, CString, NulError};
+//! Safe wrappers around the parts of flite used by tts-loop
+//!
+//! Flite keeps process wide state that is not protected against concurrent
+//! access: the voice and language lists, lexicon addenda and the jump buffer
+//! used for error handling. Every call into flite made by this crate holds a
+//! single global lock, so all functions here can be called from any thread.
+//! Synthesis requests from different threads run one at a time. Use
+//! [`SynthesisPool`] to synthesize a batch of texts in parallel worker
+//! processes, each with its own copy of flite.
+
+use std::{
+    any::Any,
+    ffi::{c_void, CStr, CString, NulError},
+    fmt, io,
+    os::raw::c_int,
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+mod pool;
+
+pub use pool::{run_worker_if_requested, SynthesisPool};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Text or voice name contains a nul byte
+    Nul(NulError),
+    /// No built in voice has this name and it can't be loaded as a voice file
+    UnknownVoice(String),
+    /// A [`SynthesisPool`] worker process could not be started or stopped
+    /// responding
+    Worker(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Nul(_) => write!(f, "Invalid C string"),
+            Error::UnknownVoice(voice) => write!(f, "Unknown flite voice \"{}\"", voice),
+            Error::Worker(e) => write!(f, "Synthesis worker failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Nul(e) => Some(e),
+            Error::UnknownVoice(_) => None,
+            Error::Worker(e) => Some(e),
+        }
+    }
+}
+
+impl From<NulError> for Error {
+    fn from(e: NulError) -> Error {
+        Error::Nul(e)
+    }
+}
 
 pub struct FliteWav {
     wav: *mut flite_sys::cst_wave,
@@ -9,6 +67,21 @@ impl FliteWav {
         FliteWav { wav }
     }
 
+    /// Wave holding a copy of samples, interleaved if there are several
+    /// channels
+    fn from_samples(samples: &[i16], sample_rate: i32, num_channels: i32) -> FliteWav {
+        let num_channels = num_channels.max(1);
+
+        let _guard = flite_lock();
+        unsafe {
+            let wav = flite_sys::new_wave();
+            flite_sys::cst_wave_resize(wav, samples.len() as c_int / num_channels, num_channels);
+            (*wav).sample_rate = sample_rate;
+            std::ptr::copy_nonoverlapping(samples.as_ptr(), (*wav).samples, samples.len());
+            FliteWav::new(wav)
+        }
+    }
+
     pub fn sample_rate(&self) -> i32 {
         unsafe { (*self.wav).sample_rate }
     }
@@ -37,10 +110,22 @@ impl std::ops::Deref for FliteWav {
     }
 }
 
+// Once synthesized the wave is plain memory owned by FliteWav, flite does not
+// hold on to it
 unsafe impl Send for FliteWav {}
 unsafe impl Sync for FliteWav {}
 
 static FLATE_INIT: std::sync::Once = std::sync::Once::new();
+static FLITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Initializes flite if needed and serializes access to it for as long as the
+/// guard is held
+fn flite_lock() -> MutexGuard<'static, ()> {
+    FLATE_INIT.call_once(flite_init);
+    // Nothing done under the lock can leave flite half modified on a panic, so
+    // a poisoned lock is still usable
+    FLITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 fn flite_init() {
     unsafe {
@@ -50,39 +135,339 @@ fn flite_init() {
     }
 }
 
+unsafe fn registered_voices() -> Vec<*mut flite_sys::cst_voice> {
+    let mut it = flite_sys::flite_voice_list as *const flite_sys::cst_val;
+    let mut ret = Vec::new();
+
+    while !it.is_null() {
+        ret.push(flite_sys::val_voice(flite_sys::val_car(it)));
+        it = flite_sys::val_cdr(it);
+    }
+
+    ret
+}
+
+unsafe fn voice_lexicon(voice: *mut flite_sys::cst_voice) -> *mut flite_sys::cst_lexicon {
+    let name = CString::new("lexicon").unwrap();
+    flite_sys::val_lexicon(flite_sys::feat_val((*voice).features, name.as_ptr()))
+}
+
+/// Lexicons used by the registered voices. Several voices share the same
+/// lexicon, so each one is only returned once
+unsafe fn registered_lexicons() -> Vec<*mut flite_sys::cst_lexicon> {
+    let mut ret = Vec::new();
+    for voice in registered_voices() {
+        let lex = voice_lexicon(voice);
+        if !lex.is_null() && !ret.contains(&lex) {
+            ret.push(lex);
+        }
+    }
+    ret
+}
+
 pub fn list_voices() -> Vec<&'static str> {
-    FLATE_INIT.call_once(flite_init);
+    let _guard = flite_lock();
+
+    unsafe {
+        registered_voices()
+            .into_iter()
+            .map(|voice| {
+                let name = CStr::from_ptr((*voice).name);
+                name.to_str().expect("Invalid voice name")
+            })
+            .collect()
+    }
+}
+
+/// Word/pronunciation pair that overrides flite's lexicon and letter to sound
+/// rules
+#[derive(Clone)]
+pub struct LexEntry {
+    pub word: String,
+    pub phones: Vec<String>,
+}
+
+/// Phones accepted by the lexicon of the registered voices
+pub fn lex_phones() -> Vec<String> {
+    let _guard = flite_lock();
+
+    let mut ret: Vec<String> = Vec::new();
+
+    unsafe {
+        for lex in registered_lexicons() {
+            let mut it = (*lex).phone_table;
+            while !it.is_null() && !(*it).is_null() {
+                let phone = CStr::from_ptr(*it).to_string_lossy().into_owned();
+                if !ret.contains(&phone) {
+                    ret.push(phone);
+                }
+                it = it.add(1);
+            }
+        }
+    }
+
+    ret
+}
+
+/// Replaces the lexicon addenda of every registered voice with the given
+/// entries. Phones not in the lexicon's phoneset are dropped by flite, callers
+/// should validate against [`lex_phones`] first
+pub fn set_lex_addenda(entries: &[LexEntry]) -> Result<(), NulError> {
+    let _guard = flite_lock();
+
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            let entry = format!("{} : {}", entry.word, entry.phones.join(" "));
+            CString::new(entry)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     unsafe {
-        let mut it = flite_sys::flite_voice_list as *const flite_sys::cst_val;
-        let mut ret = Vec::new();
+        for lex in registered_lexicons() {
+            let mut addenda: *mut flite_sys::cst_val = std::ptr::null_mut();
+            for entry in &entries {
+                let val = flite_sys::cst_lex_make_entry(lex, entry.as_ptr());
+                addenda = flite_sys::cons_val(val, addenda);
+            }
+            let addenda = flite_sys::val_reverse(addenda);
 
+            if !(*lex).lex_addenda.is_null() {
+                flite_sys::delete_val((*lex).lex_addenda);
+            }
+            (*lex).lex_addenda = addenda;
+        }
+    }
+
+    pool::record_lex_addenda(entries);
+    Ok(())
+}
+
+/// Pronunciation of a single word as given by the voice's lexicon, falling
+/// back to letter to sound rules for unknown words. Phones keep their stress
+/// markers, e.g. "dh eh1 r" for "there"
+pub fn word_to_phones<S: Into<Vec<u8>>>(word: S, voice: String) -> Result<Vec<String>, NulError> {
+    let _guard = flite_lock();
+
+    let word = CString::new(word)?;
+    let voice = CString::new(voice)?;
+
+    let mut ret = Vec::new();
+
+    unsafe {
+        let voice = flite_sys::flite_voice_select(voice.as_ptr());
+        if voice.is_null() {
+            return Ok(ret);
+        }
+
+        let lex = voice_lexicon(voice);
+        let phones = flite_sys::lex_lookup(lex, word.as_ptr(), std::ptr::null(), std::ptr::null());
+
+        let mut it = phones as *const flite_sys::cst_val;
         while !it.is_null() {
-            let voice = flite_sys::val_voice(flite_sys::val_car(it));
-            let name = CStr::from_ptr((*voice).name);
-            ret.push(name.to_str().expect("Invalid voice name"));
+            let phone = CStr::from_ptr(flite_sys::val_string(flite_sys::val_car(it)));
+            ret.push(phone.to_string_lossy().into_owned());
             it = flite_sys::val_cdr(it);
         }
 
-        ret
+        flite_sys::delete_val(phones);
     }
+
+    Ok(ret)
+}
+
+/// A token from the input text and the words flite expands it to, e.g. "5" to
+/// ["five"] or "km" to ["kilometers"]
+pub struct NormalizedToken {
+    pub token: String,
+    pub words: Vec<String>,
 }
 
-pub fn text_to_wave<S: Into<Vec<u8>>>(
+unsafe fn item_name(item: *const flite_sys::cst_item) -> String {
+    let name = CString::new("name").unwrap();
+    CStr::from_ptr(flite_sys::item_feat_string(item, name.as_ptr()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Runs flite's tokenization and token to words expansion on text without
+/// synthesizing it, using the text analysis of voice
+pub fn normalize<S: Into<Vec<u8>>>(
     text: S,
-    sample_rate: i32,
     voice: String,
-) -> Result<FliteWav, NulError> {
-    FLATE_INIT.call_once(flite_init);
+) -> Result<Vec<NormalizedToken>, NulError> {
+    let _guard = flite_lock();
 
-    let wav = unsafe {
-        let voice = CString::new(voice)?;
+    let text = CString::new(text)?;
+    let voice = CString::new(voice)?;
+    let token_rel = CString::new("Token").unwrap();
+    let tokenizer_hook = CString::new("tokenizer_func").unwrap();
+    let textanalysis_hook = CString::new("textanalysis_func").unwrap();
+
+    let mut ret = Vec::new();
+
+    unsafe {
         let voice = flite_sys::flite_voice_select(voice.as_ptr());
+        if voice.is_null() {
+            return Ok(ret);
+        }
+
+        let utt = flite_sys::new_utterance();
+        flite_sys::utt_set_input_text(utt, text.as_ptr());
+        flite_sys::utt_init(utt, voice);
+
+        // Same first two steps as utt_synth, stopping before any audio work
+        let modules = [
+            flite_sys::cst_synth_module {
+                hookname: tokenizer_hook.as_ptr(),
+                defhook: Some(flite_sys::default_tokenization),
+            },
+            flite_sys::cst_synth_module {
+                hookname: textanalysis_hook.as_ptr(),
+                defhook: Some(flite_sys::default_textanalysis),
+            },
+        ];
+
+        for module in &modules {
+            if flite_sys::apply_synth_module(utt, module).is_null() {
+                flite_sys::delete_utterance(utt);
+                return Ok(ret);
+            }
+        }
+
+        let mut token = flite_sys::relation_head(flite_sys::utt_relation(utt, token_rel.as_ptr()));
+        while !token.is_null() {
+            let mut words = Vec::new();
+            let mut word = flite_sys::item_first_daughter(token);
+            while !word.is_null() {
+                words.push(item_name(word));
+                word = flite_sys::item_next(word);
+            }
+
+            ret.push(NormalizedToken {
+                token: item_name(token),
+                words,
+            });
+            token = flite_sys::item_next(token);
+        }
+
+        flite_sys::delete_utterance(utt);
+    }
+
+    Ok(ret)
+}
+
+/// Voice called name, must be called with the flite lock held
+unsafe fn select_voice(name: String) -> Result<*mut flite_sys::cst_voice, Error> {
+    let c_name = CString::new(name.as_str())?;
+    let voice = flite_sys::flite_voice_select(c_name.as_ptr());
+    if voice.is_null() {
+        return Err(Error::UnknownVoice(name));
+    }
+    Ok(voice)
+}
+
+/// Synthesizes text with voice. The wave is at the voice's native sample rate,
+/// see [`FliteWav::sample_rate`]
+pub fn text_to_wave<S: Into<Vec<u8>>>(text: S, voice: String) -> Result<FliteWav, Error> {
+    let _guard = flite_lock();
+
+    let wav = unsafe {
+        let voice = select_voice(voice)?;
+        let text = CString::new(text)?;
+        flite_sys::flite_text_to_wave(text.as_ptr(), voice)
+    };
+
+    Ok(FliteWav::new(wav))
+}
+
+/// Streaming callback and the panic it raised, if any. A panic can't unwind
+/// through flite, so it is held until flite returns
+struct StreamState<F> {
+    chunk_callback: F,
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn stream_chunk<F: FnMut(&[i16], i32) -> bool>(
+    wav: *const flite_sys::cst_wave,
+    start: c_int,
+    size: c_int,
+    _last: c_int,
+    asi: *mut flite_sys::cst_audio_streaming_info,
+) -> c_int {
+    // CST_AUDIO_STREAM_CONT and CST_AUDIO_STREAM_STOP
+    const CONTINUE: c_int = 0;
+    const STOP: c_int = -1;
+
+    let state = &mut *((*asi).userdata as *mut StreamState<F>);
+    if state.panic.is_some() {
+        return STOP;
+    }
+
+    let samples = std::slice::from_raw_parts((*wav).samples.offset(start as isize), size as usize);
+    let sample_rate = (*wav).sample_rate;
+    let chunk_callback = &mut state.chunk_callback;
+
+    match panic::catch_unwind(AssertUnwindSafe(|| chunk_callback(samples, sample_rate))) {
+        Ok(true) => CONTINUE,
+        Ok(false) => STOP,
+        Err(panic) => {
+            state.panic = Some(panic);
+            STOP
+        }
+    }
+}
+
+/// Same as [`text_to_wave`], but chunk_callback is handed audio as soon as flite
+/// produces it. Chunks are mono and at the voice's native sample rate, which is
+/// passed alongside them. Returning false from chunk_callback stops synthesis
+/// early. The returned wave holds all the audio
+///
+/// chunk_callback runs while flite's lock is held, calling any function of
+/// this crate from it deadlocks. A panic in chunk_callback stops synthesis and
+/// is resumed once flite returns
+pub fn text_to_wave_streaming<S, F>(
+    text: S,
+    voice: String,
+    chunk_callback: F,
+) -> Result<FliteWav, Error>
+where
+    S: Into<Vec<u8>>,
+    F: FnMut(&[i16], i32) -> bool,
+{
+    let _guard = flite_lock();
+
+    let mut state = StreamState {
+        chunk_callback,
+        panic: None,
+    };
+
+    let wav = unsafe {
+        let voice = select_voice(voice)?;
         let text = CString::new(text)?;
+
+        let asi = flite_sys::new_audio_streaming_info();
+        (*asi).asc = Some(stream_chunk::<F>);
+        (*asi).userdata = &mut state as *mut StreamState<F> as *mut c_void;
+
+        // The voice owns asi from here on and frees it when the feature is
+        // removed
+        let streaming_info = CString::new("streaming_info").unwrap();
+        flite_sys::feat_set(
+            (*voice).features,
+            streaming_info.as_ptr(),
+            flite_sys::audio_streaming_info_val(asi),
+        );
+
         let wav = flite_sys::flite_text_to_wave(text.as_ptr(), voice);
-        flite_sys::cst_wave_resample(wav, sample_rate);
+        flite_sys::feat_remove((*voice).features, streaming_info.as_ptr());
         wav
     };
 
-    Ok(FliteWav::new(wav))
+    let wav = FliteWav::new(wav);
+    if let Some(panic) = state.panic {
+        panic::resume_unwind(panic);
+    }
+
+    Ok(wav)
 }
@@ -0,0 +1,394 @@
+use crate::{Error, FliteWav, LexEntry};
+
+use std::{
+    env,
+    ffi::CString,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+};
+
+/// Set in the environment of worker processes
+const WORKER_ENV: &str = "FLITE_SYNTHESIS_WORKER";
+
+// Requests sent to a worker
+const SET_LEX_ADDENDA: u8 = 0;
+const SYNTHESIZE: u8 = 1;
+
+// Responses to SYNTHESIZE
+const WAVE: u8 = 0;
+const UNKNOWN_VOICE: u8 = 1;
+
+type SynthesisResult = Result<FliteWav, Error>;
+
+/// Entries last given to [`set_lex_addenda`](crate::set_lex_addenda), and how
+/// often they changed, so that workers can catch up before their next text
+struct LexAddenda {
+    generation: u64,
+    entries: Vec<LexEntry>,
+}
+
+static LEX_ADDENDA: Mutex<LexAddenda> = Mutex::new(LexAddenda {
+    generation: 0,
+    entries: Vec::new(),
+});
+
+pub(crate) fn record_lex_addenda(entries: &[LexEntry]) {
+    let mut addenda = LEX_ADDENDA.lock().unwrap_or_else(PoisonError::into_inner);
+    addenda.generation += 1;
+    addenda.entries = entries.to_vec();
+}
+
+struct Job {
+    idx: usize,
+    text: String,
+    voice: String,
+    result_tx: Sender<(usize, SynthesisResult)>,
+}
+
+/// Synthesizes batches of texts in parallel
+///
+/// Flite's global state only allows one synthesis at a time per process (see
+/// the crate docs), so every worker is a copy of the current executable
+/// started as a separate process. Executables that create a pool have to call
+/// [`run_worker_if_requested`] first thing in main. Lexicon addenda are passed
+/// on to the workers before their next text
+pub struct SynthesisPool {
+    job_tx: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl SynthesisPool {
+    pub fn new(num_workers: usize) -> Result<SynthesisPool, Error> {
+        let program = env::current_exe().map_err(Error::Worker)?;
+
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let workers = (0..num_workers.max(1))
+            .map(|_| {
+                let process = WorkerProcess::spawn(&program)?;
+                let program = program.clone();
+                let job_rx = Arc::clone(&job_rx);
+                Ok(std::thread::spawn(move || {
+                    worker(program, Some(process), job_rx)
+                }))
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(Error::Worker)?;
+
+        Ok(SynthesisPool {
+            job_tx: Some(job_tx),
+            workers,
+        })
+    }
+
+    /// Queues texts for synthesis. Results arrive on the returned receiver in
+    /// completion order, tagged with the index of their text
+    pub fn submit(&self, texts: Vec<String>, voice: &str) -> Receiver<(usize, SynthesisResult)> {
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let job_tx = self.job_tx.as_ref().expect("Pool already shut down");
+        for (idx, text) in texts.into_iter().enumerate() {
+            let job = Job {
+                idx,
+                text,
+                voice: voice.to_string(),
+                result_tx: result_tx.clone(),
+            };
+
+            // Workers only exit once job_tx is dropped
+            job_tx.send(job).expect("Synthesis workers exited");
+        }
+
+        result_rx
+    }
+
+    /// Synthesizes all texts, returning the results in the same order as texts
+    pub fn synthesize_all(&self, texts: Vec<String>, voice: &str) -> Vec<SynthesisResult> {
+        let num_texts = texts.len();
+        let mut results = self
+            .submit(texts, voice)
+            .iter()
+            .take(num_texts)
+            .collect::<Vec<_>>();
+
+        results.sort_by_key(|(idx, _)| *idx);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+impl Drop for SynthesisPool {
+    fn drop(&mut self) {
+        // Closing the job channel lets every worker fall out of its loop
+        self.job_tx.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(program: PathBuf, mut process: Option<WorkerProcess>, job_rx: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match job_rx.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+
+        let job = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let result = match process
+            .take()
+            .map_or_else(|| WorkerProcess::spawn(&program), Ok)
+        {
+            Ok(mut running) => {
+                let result = running.synthesize(&job.text, &job.voice);
+                // A process that failed is replaced for the next text
+                if !matches!(result, Err(Error::Worker(_))) {
+                    process = Some(running);
+                }
+                result
+            }
+            Err(e) => Err(Error::Worker(e)),
+        };
+
+        let _ = job.result_tx.send((job.idx, result));
+    }
+}
+
+struct WorkerProcess {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    /// Generation of the lexicon addenda the process has
+    lex_generation: u64,
+}
+
+impl WorkerProcess {
+    fn spawn(program: &Path) -> io::Result<WorkerProcess> {
+        let mut child = Command::new(program)
+            .env(WORKER_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // Both are piped above
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Ok(WorkerProcess {
+            child,
+            stdin,
+            stdout,
+            lex_generation: 0,
+        })
+    }
+
+    fn synthesize(&mut self, text: &str, voice: &str) -> SynthesisResult {
+        // Same errors as text_to_wave for text flite can't take
+        CString::new(text)?;
+        CString::new(voice)?;
+
+        self.update_lex_addenda().map_err(Error::Worker)?;
+        write_synthesize(&mut self.stdin, text, voice).map_err(Error::Worker)?;
+        self.stdin.flush().map_err(Error::Worker)?;
+        read_response(&mut self.stdout).map_err(Error::Worker)?
+    }
+
+    fn update_lex_addenda(&mut self) -> io::Result<()> {
+        let addenda = LEX_ADDENDA.lock().unwrap_or_else(PoisonError::into_inner);
+        if addenda.generation == self.lex_generation {
+            return Ok(());
+        }
+
+        self.stdin.write_all(&[SET_LEX_ADDENDA])?;
+        write_u32(&mut self.stdin, addenda.entries.len() as u32)?;
+        for entry in &addenda.entries {
+            write_str(&mut self.stdin, &entry.word)?;
+            write_str(&mut self.stdin, &entry.phones.join(" "))?;
+        }
+
+        self.lex_generation = addenda.generation;
+        Ok(())
+    }
+}
+
+impl Drop for WorkerProcess {
+    fn drop(&mut self) {
+        // Workers only ever wait for the next request when they are dropped
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Turns this process into a synthesis worker if it was started by a
+/// [`SynthesisPool`]. The worker serves requests until the pool goes away, then
+/// exits the process. In any other process this returns right away
+pub fn run_worker_if_requested() {
+    if env::var_os(WORKER_ENV).is_none() {
+        return;
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = serve(&mut stdin.lock(), &mut BufWriter::new(stdout.lock()));
+    std::process::exit(if result.is_ok() { 0 } else { 1 });
+}
+
+fn serve<R: Read, W: Write>(input: &mut R, output: &mut W) -> io::Result<()> {
+    loop {
+        let request = match read_u8(input) {
+            Ok(request) => request,
+            // The pool closed the pipe
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        match request {
+            SET_LEX_ADDENDA => {
+                let num_entries = read_u32(input)?;
+                let mut entries = Vec::new();
+                for _ in 0..num_entries {
+                    let word = read_string(input)?;
+                    let phones = read_string(input)?
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect();
+                    entries.push(LexEntry { word, phones });
+                }
+
+                crate::set_lex_addenda(&entries).map_err(invalid_data)?;
+            }
+            SYNTHESIZE => {
+                let voice = read_string(input)?;
+                let text = read_string(input)?;
+
+                match crate::text_to_wave(text, voice) {
+                    Ok(wav) => {
+                        output.write_all(&[WAVE])?;
+                        write_u32(output, wav.sample_rate() as u32)?;
+                        write_u32(output, wav.num_channels() as u32)?;
+                        write_u32(output, wav.len() as u32)?;
+                        let bytes = wav
+                            .iter()
+                            .flat_map(|sample| sample.to_le_bytes().to_vec())
+                            .collect::<Vec<_>>();
+                        output.write_all(&bytes)?;
+                    }
+                    Err(Error::UnknownVoice(voice)) => {
+                        output.write_all(&[UNKNOWN_VOICE])?;
+                        write_str(output, &voice)?;
+                    }
+                    Err(e) => return Err(invalid_data(e)),
+                }
+
+                output.flush()?;
+            }
+            _ => return Err(invalid_data(format!("Unknown request {}", request))),
+        }
+    }
+}
+
+fn write_synthesize<W: Write>(output: &mut W, text: &str, voice: &str) -> io::Result<()> {
+    output.write_all(&[SYNTHESIZE])?;
+    write_str(output, voice)?;
+    write_str(output, text)
+}
+
+fn read_response<R: Read>(input: &mut R) -> io::Result<SynthesisResult> {
+    match read_u8(input)? {
+        WAVE => {
+            let sample_rate = read_u32(input)? as i32;
+            let num_channels = read_u32(input)? as i32;
+            let len = read_u32(input)? as usize;
+
+            let mut bytes = vec![0; len * 2];
+            input.read_exact(&mut bytes)?;
+            let samples = bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect::<Vec<_>>();
+
+            Ok(Ok(FliteWav::from_samples(
+                &samples,
+                sample_rate,
+                num_channels,
+            )))
+        }
+        UNKNOWN_VOICE => Ok(Err(Error::UnknownVoice(read_string(input)?))),
+        response => Err(invalid_data(format!("Unknown response {}", response))),
+    }
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn read_u8<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(input: &mut R) -> io::Result<String> {
+    let len = read_u32(input)? as usize;
+    let mut buf = vec![0; len];
+    input.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(invalid_data)
+}
+
+fn write_u32<W: Write>(output: &mut W, value: u32) -> io::Result<()> {
+    output.write_all(&value.to_le_bytes())
+}
+
+fn write_str<W: Write>(output: &mut W, s: &str) -> io::Result<()> {
+    write_u32(output, s.len() as u32)?;
+    output.write_all(s.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_round_trips_synthesis() {
+        let voice = crate::list_voices()[0].to_string();
+        let expected = crate::text_to_wave("Hello world", voice.clone()).unwrap();
+
+        let mut request = Vec::new();
+        write_synthesize(&mut request, "Hello world", &voice).unwrap();
+        write_synthesize(&mut request, "Hello world", "no_such_voice").unwrap();
+
+        let mut response = Vec::new();
+        serve(&mut request.as_slice(), &mut response).unwrap();
+
+        let mut response = response.as_slice();
+        let wav = read_response(&mut response).unwrap().unwrap();
+        assert_eq!(wav.sample_rate(), expected.sample_rate());
+        assert_eq!(wav.num_channels(), expected.num_channels());
+        assert_eq!(&*wav, &*expected);
+
+        match read_response(&mut response).unwrap() {
+            Err(Error::UnknownVoice(voice)) => assert_eq!(voice, "no_such_voice"),
+            _ => panic!("Expected an unknown voice"),
+        }
+        assert!(response.is_empty());
+    }
+}
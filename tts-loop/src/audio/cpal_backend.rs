@@ -0,0 +1,186 @@
+//! Audio devices of the system, through cpal
+
+use super::{AudioBackend, Error, ErrorCallback, InputCallback, OutputCallback, Stream};
+use crate::devices::{self, Direction};
+
+use cpal::traits::*;
+use cpal::{
+    Device, Sample, SampleFormat, SampleRate, StreamConfig, SupportedStreamConfig,
+    SupportedStreamConfigRange,
+};
+
+pub(crate) struct CpalBackend;
+
+impl AudioBackend for CpalBackend {
+    fn input_stream(
+        &self,
+        device: Option<&str>,
+        sample_rate: u32,
+        callback: InputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Stream, Error> {
+        let input_dev = devices::find(Direction::Input, device)?;
+
+        let supported_configs = input_dev
+            .supported_input_configs()
+            .map_err(|_| Error::SupportedStreamConfigsError)?;
+        let config = pick_config(supported_configs, sample_rate)?;
+
+        let stream = open_input(&input_dev, &config, callback, error_callback)?;
+        Ok(Box::new(stream))
+    }
+
+    fn output_stream(
+        &self,
+        device: Option<&str>,
+        sample_rate: u32,
+        callback: OutputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<(Stream, u32), Error> {
+        let output_dev = devices::find(Direction::Output, device)?;
+
+        let supported_configs = output_dev
+            .supported_output_configs()
+            .map_err(|_| Error::SupportedStreamConfigsError)?;
+        let config = pick_config(supported_configs, sample_rate)?;
+
+        let stream = open_output(&output_dev, &config, callback, error_callback)?;
+        Ok((Box::new(stream), config.sample_rate().0))
+    }
+}
+
+/// Device config that needs the least conversion from 16 bit mono audio at
+/// sample_rate. A different rate costs the most, so configs are ranked by how
+/// close they get to sample_rate, then by channel count, then by format
+fn pick_config<I>(configs: I, sample_rate: u32) -> Result<SupportedStreamConfig, Error>
+where
+    I: Iterator<Item = SupportedStreamConfigRange>,
+{
+    let closest_rate = |config: &SupportedStreamConfigRange| {
+        sample_rate
+            .max(config.min_sample_rate().0)
+            .min(config.max_sample_rate().0)
+    };
+
+    let format_rank = |format: SampleFormat| match format {
+        SampleFormat::I16 => 0,
+        SampleFormat::F32 => 1,
+        SampleFormat::U16 => 2,
+    };
+
+    let config = configs
+        .min_by_key(|config| {
+            (
+                (closest_rate(config) as i64 - sample_rate as i64).abs(),
+                config.channels(),
+                format_rank(config.sample_format()),
+            )
+        })
+        .ok_or(Error::UnsupportedConfig)?;
+
+    let rate = closest_rate(&config);
+    Ok(config.with_sample_rate(SampleRate(rate)))
+}
+
+/// Input stream in the device's format, handing mono 16 bit audio to callback.
+/// Channels are mixed down by averaging
+fn build_input_stream<T: Sample>(
+    device: &Device,
+    config: &StreamConfig,
+    mut callback: InputCallback,
+    mut error_callback: ErrorCallback,
+) -> Result<cpal::Stream, Error> {
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+
+    let stream = device.build_input_stream(
+        config,
+        move |samples: &[T], _| {
+            let mono = samples
+                .chunks(channels)
+                .map(|frame| {
+                    let sum = frame.iter().map(|s| s.to_i16() as i32).sum::<i32>();
+                    (sum / frame.len() as i32) as i16
+                })
+                .collect::<Vec<_>>();
+            callback(&mono, sample_rate);
+        },
+        move |err| error_callback(err.into()),
+    )?;
+
+    Ok(stream)
+}
+
+/// Output stream in the device's format, filled with mono 16 bit audio by
+/// callback. Every channel plays the same audio
+fn build_output_stream<T: Sample>(
+    device: &Device,
+    config: &StreamConfig,
+    mut callback: OutputCallback,
+    mut error_callback: ErrorCallback,
+) -> Result<cpal::Stream, Error> {
+    let channels = config.channels as usize;
+    let mut mono = Vec::new();
+
+    let stream = device.build_output_stream(
+        config,
+        move |samples: &mut [T], _| {
+            mono.clear();
+            mono.resize(samples.len() / channels, 0i16);
+            // The device asks for audio until the stream is dropped, so the end
+            // of playback is left to whoever owns the stream
+            callback(&mut mono);
+
+            for (frame, sample) in samples.chunks_mut(channels).zip(&mono) {
+                for out in frame {
+                    *out = T::from(sample);
+                }
+            }
+        },
+        move |err| error_callback(err.into()),
+    )?;
+
+    Ok(stream)
+}
+
+/// Input stream on device with config, converting to mono 16 bit audio
+fn open_input(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    callback: InputCallback,
+    error_callback: ErrorCallback,
+) -> Result<cpal::Stream, Error> {
+    let stream_config = config.config();
+    match config.sample_format() {
+        SampleFormat::I16 => {
+            build_input_stream::<i16>(device, &stream_config, callback, error_callback)
+        }
+        SampleFormat::U16 => {
+            build_input_stream::<u16>(device, &stream_config, callback, error_callback)
+        }
+        SampleFormat::F32 => {
+            build_input_stream::<f32>(device, &stream_config, callback, error_callback)
+        }
+    }
+}
+
+/// Output stream on device with config, converting from mono 16 bit audio
+fn open_output(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    callback: OutputCallback,
+    error_callback: ErrorCallback,
+) -> Result<cpal::Stream, Error> {
+    let stream_config = config.config();
+    match config.sample_format() {
+        SampleFormat::I16 => {
+            build_output_stream::<i16>(device, &stream_config, callback, error_callback)
+        }
+        SampleFormat::U16 => {
+            build_output_stream::<u16>(device, &stream_config, callback, error_callback)
+        }
+        SampleFormat::F32 => {
+            build_output_stream::<f32>(device, &stream_config, callback, error_callback)
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{
+    devices::{self, Direction},
+    resample::{ResampleQuality, Resampler},
+};
+
+use thiserror::Error as ThisError;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, RecvTimeoutError, TryRecvError},
+    Arc,
+};
+use std::time::Duration;
+
+mod cpal_backend;
+mod virtual_backend;
+
+pub use virtual_backend::VirtualAudio;
+
+/// Playback fails if the output device stops asking for audio for this long,
+/// which is how some hosts report an unplugged device
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Device(#[from] devices::Error),
+    #[error("Error retrieving supported device configurations")]
+    SupportedStreamConfigsError,
+    #[error("Device does not report any configuration")]
+    UnsupportedConfig,
+    #[error(transparent)]
+    DeviceError(#[from] cpal::DevicesError),
+    #[error(transparent)]
+    BuildStreamError(#[from] cpal::BuildStreamError),
+    #[error("Audio stream failed: {0}")]
+    Stream(#[from] cpal::StreamError),
+    #[error("Playback stalled, the output device stopped asking for audio")]
+    PlaybackStalled,
+    #[error("Failed to read virtual input {path}: {err}")]
+    VirtualInput { path: PathBuf, err: hound::Error },
+    #[error("Failed to write virtual output {path}: {err}")]
+    VirtualOutput { path: PathBuf, err: hound::Error },
+    #[error("Virtual audio speed must be a positive number, got {0}")]
+    InvalidSpeed(f64),
+}
+
+/// Keeps a stream running until dropped
+pub(crate) type Stream = Box<dyn std::any::Any>;
+
+/// Receives recorded mono audio and its sample rate
+pub(crate) type InputCallback = Box<dyn FnMut(&[i16], u32) + Send>;
+
+/// Fills a buffer with mono audio to play. Returns true once there is nothing
+/// left to play
+pub(crate) type OutputCallback = Box<dyn FnMut(&mut [i16]) -> bool + Send>;
+
+/// Called if a stream fails, after which it delivers no more audio
+pub(crate) type ErrorCallback = Box<dyn FnMut(Error) + Send>;
+
+/// Where audio is recorded from and played to. device is a name from
+/// [`crate::device_names`], backends without devices ignore it
+pub(crate) trait AudioBackend {
+    /// Whether the device names passed to streams mean anything
+    fn has_devices(&self) -> bool {
+        true
+    }
+
+    /// Starts recording at a rate close to sample_rate
+    fn input_stream(
+        &self,
+        device: Option<&str>,
+        sample_rate: u32,
+        callback: InputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Stream, Error>;
+
+    /// Starts playback at a rate close to sample_rate. Returns the rate
+    /// callback has to provide audio at
+    fn output_stream(
+        &self,
+        device: Option<&str>,
+        sample_rate: u32,
+        callback: OutputCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<(Stream, u32), Error>;
+}
+
+/// Completion and errors reported by the callbacks of an output stream
+struct PlaybackStatus {
+    rx: mpsc::Receiver<Result<(), Error>>,
+    /// Times the stream asked for audio, to tell a stalled device apart from a
+    /// long buffer
+    callbacks: Arc<AtomicUsize>,
+}
+
+impl PlaybackStatus {
+    /// Output stream of backend reporting to a new status. callback returns
+    /// true once everything has been played
+    fn open<F>(
+        backend: &dyn AudioBackend,
+        device: Option<&str>,
+        sample_rate: u32,
+        mut callback: F,
+    ) -> Result<(Stream, u32, PlaybackStatus), Error>
+    where
+        F: FnMut(&mut [i16]) -> bool + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let error_tx = tx.clone();
+        let callbacks = Arc::new(AtomicUsize::new(0));
+        let stream_callbacks = Arc::clone(&callbacks);
+
+        let (stream, sample_rate) = backend.output_stream(
+            device,
+            sample_rate,
+            Box::new(move |buf| {
+                stream_callbacks.fetch_add(1, Ordering::Relaxed);
+                let finished = callback(buf);
+                if finished {
+                    let _ = tx.send(Ok(()));
+                }
+                finished
+            }),
+            Box::new(move |err| {
+                let _ = error_tx.send(Err(err));
+            }),
+        )?;
+
+        Ok((stream, sample_rate, PlaybackStatus { rx, callbacks }))
+    }
+
+    /// Blocks until the stream reports completion. Fails if it reports an
+    /// error or stops asking for audio for STALL_TIMEOUT
+    fn wait(&self) -> Result<(), Error> {
+        let mut last_callbacks = self.callbacks.load(Ordering::Relaxed);
+
+        loop {
+            match self.rx.recv_timeout(STALL_TIMEOUT) {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(e)) => return Err(e),
+                Err(RecvTimeoutError::Timeout) => {
+                    let callbacks = self.callbacks.load(Ordering::Relaxed);
+                    if callbacks == last_callbacks {
+                        return Err(Error::PlaybackStalled);
+                    }
+                    last_callbacks = callbacks;
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::PlaybackStalled),
+            }
+        }
+    }
+}
+
+/// Output stream fed with audio as it becomes available. Gaps in the input are
+/// played as silence
+pub(crate) struct StreamingPlayback {
+    _stream: Stream,
+    tx: mpsc::Sender<Vec<i16>>,
+    status: PlaybackStatus,
+    resampler: Resampler,
+}
+
+impl StreamingPlayback {
+    pub(crate) fn push(&mut self, buf: &[i16]) {
+        let _ = self.tx.send(self.resampler.process(buf));
+    }
+
+    /// Blocks until everything pushed so far has been played, or the device
+    /// fails
+    pub(crate) fn finish_blocking(self) -> Result<(), Error> {
+        let _ = self.tx.send(self.resampler.finish());
+        // Closing the channel tells the output stream no more audio is coming
+        drop(self.tx);
+        self.status.wait()
+    }
+}
+
+/// Converts recorded audio to the rate it was asked for. What the resampler
+/// holds back is handed over when the stream drops the converter, so nothing
+/// recorded is lost when recording stops
+struct InputConverter<F: FnMut(&[i16])> {
+    callback: F,
+    sample_rate: u32,
+    quality: ResampleQuality,
+    /// Set up once the first audio arrives, backends only report their rate
+    /// with it
+    resampler: Option<Resampler>,
+}
+
+impl<F: FnMut(&[i16])> InputConverter<F> {
+    fn process(&mut self, samples: &[i16], input_rate: u32) {
+        let (sample_rate, quality) = (self.sample_rate, self.quality);
+        let resampler = self
+            .resampler
+            .get_or_insert_with(|| Resampler::new(input_rate, sample_rate, quality));
+        (self.callback)(&resampler.process(samples));
+    }
+}
+
+impl<F: FnMut(&[i16])> Drop for InputConverter<F> {
+    fn drop(&mut self) {
+        if let Some(resampler) = self.resampler.take() {
+            (self.callback)(&resampler.finish());
+        }
+    }
+}
+
+pub(crate) struct AudioManager {
+    backend: Box<dyn AudioBackend>,
+    /// Selected devices as listed by [`crate::device_names`]. None uses the
+    /// system default
+    input_device: Option<String>,
+    output_device: Option<String>,
+    resample_quality: ResampleQuality,
+}
+
+impl AudioManager {
+    /// Uses the system's audio devices, or a virtual sound card if
+    /// virtual_audio is set
+    pub(crate) fn new(
+        virtual_audio: Option<VirtualAudio>,
+        input_device: Option<String>,
+        output_device: Option<String>,
+        resample_quality: ResampleQuality,
+    ) -> Result<AudioManager, Error> {
+        let backend: Box<dyn AudioBackend> = match virtual_audio {
+            Some(config) => Box::new(virtual_backend::VirtualBackend::new(config)?),
+            None => Box::new(cpal_backend::CpalBackend),
+        };
+
+        Ok(AudioManager {
+            backend,
+            input_device,
+            output_device,
+            resample_quality,
+        })
+    }
+
+    pub(crate) fn device(&self, direction: Direction) -> Option<&str> {
+        match direction {
+            Direction::Input => self.input_device.as_deref(),
+            Direction::Output => self.output_device.as_deref(),
+        }
+    }
+
+    /// False for a virtual sound card, which ignores the selected devices
+    pub(crate) fn has_devices(&self) -> bool {
+        self.backend.has_devices()
+    }
+
+    pub(crate) fn set_device(&mut self, direction: Direction, name: Option<String>) {
+        match direction {
+            Direction::Input => self.input_device = name,
+            Direction::Output => self.output_device = name,
+        }
+    }
+
+    pub(crate) fn set_resample_quality(&mut self, resample_quality: ResampleQuality) {
+        self.resample_quality = resample_quality;
+    }
+
+    /// Records from the selected input device. input_callback receives audio at
+    /// sample_rate. error_callback is called if the stream fails, e.g. because
+    /// the device was unplugged, after which no more audio arrives
+    pub(crate) fn input_stream<F, E>(
+        &self,
+        sample_rate: u32,
+        input_callback: F,
+        error_callback: E,
+    ) -> Result<Stream, Error>
+    where
+        F: FnMut(&[i16]) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let mut converter = InputConverter {
+            callback: input_callback,
+            sample_rate,
+            quality: self.resample_quality,
+            resampler: None,
+        };
+
+        self.backend.input_stream(
+            self.input_device.as_deref(),
+            sample_rate,
+            Box::new(move |samples, input_rate| converter.process(samples, input_rate)),
+            Box::new(error_callback),
+        )
+    }
+
+    pub(crate) fn play_buf_blocking(&self, buf: &[i16], sample_rate: u32) -> Result<(), Error> {
+        let mut playback = self.start_playback(sample_rate)?;
+        playback.push(buf);
+        playback.finish_blocking()
+    }
+
+    /// Starts playback of audio at sample_rate, pushed as it becomes available
+    pub(crate) fn start_playback(&self, sample_rate: u32) -> Result<StreamingPlayback, Error> {
+        let (tx, rx) = mpsc::channel::<Vec<i16>>();
+
+        let mut pending = VecDeque::new();
+        let mut input_finished = false;
+
+        let (_stream, output_rate, status) = PlaybackStatus::open(
+            self.backend.as_ref(),
+            self.output_device.as_deref(),
+            sample_rate,
+            move |output_buf| {
+                while !input_finished {
+                    match rx.try_recv() {
+                        Ok(buf) => pending.extend(buf),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => input_finished = true,
+                    }
+                }
+
+                // Checked before filling so that the final samples have been
+                // handed to the device by the time we report completion
+                let finished = input_finished && pending.is_empty();
+
+                for sample in output_buf.iter_mut() {
+                    *sample = pending.pop_front().unwrap_or(0);
+                }
+
+                finished
+            },
+        )?;
+
+        Ok(StreamingPlayback {
+            _stream,
+            tx,
+            status,
+            resampler: Resampler::new(sample_rate, output_rate, self.resample_quality),
+        })
+    }
+}
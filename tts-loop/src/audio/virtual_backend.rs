@@ -0,0 +1,368 @@
+//! Sound card simulated with WAV files, for machines without audio devices and
+//! for runs that need the same input every time
+
+use super::{AudioBackend, Error, ErrorCallback, InputCallback, OutputCallback, Stream};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use log::debug;
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Audio is recorded and played in blocks of this many seconds
+const BLOCK_SECS: f64 = 0.01;
+
+/// Settings of the virtual sound card
+#[derive(Clone, Debug)]
+pub struct VirtualAudio {
+    /// WAV file that is recorded, followed by silence. Silence only if None
+    pub input: Option<PathBuf>,
+    /// WAV file everything played is written to. Discarded if None
+    pub output: Option<PathBuf>,
+    /// How fast audio is recorded and played relative to real time
+    pub speed: f64,
+}
+
+impl Default for VirtualAudio {
+    fn default() -> VirtualAudio {
+        VirtualAudio {
+            input: None,
+            output: None,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Where played audio ends up. Every playback is appended
+struct Sink {
+    /// Fixed by the first playback, later ones are converted to it
+    sample_rate: Option<u32>,
+    path: Option<PathBuf>,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    /// Samples played so far, for all playbacks
+    played: u64,
+}
+
+impl Sink {
+    /// Prepares for playback at sample_rate, returning the rate audio has to be
+    /// played at
+    fn open(&mut self, sample_rate: u32) -> Result<u32, Error> {
+        let sample_rate = *self.sample_rate.get_or_insert(sample_rate);
+
+        if let (Some(path), None) = (&self.path, &self.writer) {
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            };
+            let writer = WavWriter::create(path, spec).map_err(|err| Error::VirtualOutput {
+                path: path.clone(),
+                err,
+            })?;
+            self.writer = Some(writer);
+        }
+
+        Ok(sample_rate)
+    }
+
+    fn write(&mut self, buf: &[i16]) -> Result<(), hound::Error> {
+        if let Some(writer) = &mut self.writer {
+            for sample in buf {
+                writer.write_sample(*sample)?;
+            }
+        }
+        self.played += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Makes the file valid up to here, in case the app doesn't exit cleanly
+    fn flush(&mut self) -> Result<(), hound::Error> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush()?;
+        }
+
+        let sample_rate = self.sample_rate.unwrap_or(1);
+        debug!(
+            "Virtual playback finished, {:.1}s played in total",
+            self.played as f64 / sample_rate as f64
+        );
+        Ok(())
+    }
+
+    fn output_err(&self, err: hound::Error) -> Error {
+        Error::VirtualOutput {
+            path: self.path.clone().unwrap_or_default(),
+            err,
+        }
+    }
+}
+
+/// Runs until dropped or until its thread is done
+struct VirtualStream {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for VirtualStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Calls step with a block length every BLOCK_SECS / speed seconds, as a sound
+/// card at sample_rate would. Stops once step returns false
+fn spawn_clocked<F>(sample_rate: u32, speed: f64, mut step: F) -> VirtualStream
+where
+    F: FnMut(usize) -> bool + Send + 'static,
+{
+    let block_len = ((sample_rate as f64 * BLOCK_SECS) as usize).max(1);
+    let block_duration = Duration::from_secs_f64(block_len as f64 / sample_rate as f64 / speed);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = Arc::clone(&stop);
+
+    let thread = thread::spawn(move || {
+        let mut next_block = Instant::now();
+        while !thread_stop.load(Ordering::Relaxed) && step(block_len) {
+            next_block += block_duration;
+            if let Some(wait) = next_block.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+    });
+
+    VirtualStream {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+/// Mono 16 bit samples of the WAV file at path, and their rate. Channels are
+/// mixed down by averaging
+fn read_wav(path: &Path) -> Result<(Vec<i16>, u32), hound::Error> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples = match spec.sample_format {
+        SampleFormat::Int => {
+            let shift = spec.bits_per_sample as i32 - 16;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| if shift >= 0 { s >> shift } else { s << -shift }))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| (s * i16::MAX as f32) as i32))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let mono = samples
+        .chunks(spec.channels.max(1) as usize)
+        .map(|frame| {
+            let sample = frame.iter().sum::<i32>() / frame.len() as i32;
+            sample.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        })
+        .collect();
+
+    Ok((mono, spec.sample_rate))
+}
+
+pub(crate) struct VirtualBackend {
+    input: Option<PathBuf>,
+    speed: f64,
+    sink: Arc<Mutex<Sink>>,
+}
+
+impl VirtualBackend {
+    pub(crate) fn new(config: VirtualAudio) -> Result<VirtualBackend, Error> {
+        if !(config.speed.is_finite() && config.speed > 0.0) {
+            return Err(Error::InvalidSpeed(config.speed));
+        }
+
+        Ok(VirtualBackend {
+            input: config.input,
+            speed: config.speed,
+            sink: Arc::new(Mutex::new(Sink {
+                sample_rate: None,
+                path: config.output,
+                writer: None,
+                played: 0,
+            })),
+        })
+    }
+}
+
+impl AudioBackend for VirtualBackend {
+    fn has_devices(&self) -> bool {
+        false
+    }
+
+    /// Records the input file from the start, at its own rate
+    fn input_stream(
+        &self,
+        _device: Option<&str>,
+        sample_rate: u32,
+        mut callback: InputCallback,
+        _error_callback: ErrorCallback,
+    ) -> Result<Stream, Error> {
+        let (samples, sample_rate) = match &self.input {
+            Some(path) => read_wav(path).map_err(|err| Error::VirtualInput {
+                path: path.clone(),
+                err,
+            })?,
+            None => (Vec::new(), sample_rate),
+        };
+
+        let mut pos = 0;
+        let mut block = Vec::new();
+        let stream = spawn_clocked(sample_rate, self.speed, move |block_len| {
+            let end = (pos + block_len).min(samples.len());
+            block.clear();
+            block.extend_from_slice(&samples[pos..end]);
+            block.resize(block_len, 0);
+            pos = end;
+
+            callback(&block, sample_rate);
+            true
+        });
+
+        Ok(Box::new(stream))
+    }
+
+    fn output_stream(
+        &self,
+        _device: Option<&str>,
+        sample_rate: u32,
+        mut callback: OutputCallback,
+        mut error_callback: ErrorCallback,
+    ) -> Result<(Stream, u32), Error> {
+        let sample_rate = self
+            .sink
+            .lock()
+            .expect("Virtual output poisoned")
+            .open(sample_rate)?;
+
+        let sink = Arc::clone(&self.sink);
+        let mut block = Vec::new();
+        let stream = spawn_clocked(sample_rate, self.speed, move |block_len| {
+            block.clear();
+            block.resize(block_len, 0);
+            // Unlike a device the block isn't played once callback reports the
+            // end, so the file doesn't fill up with trailing silence
+            let finished = callback(&mut block);
+
+            let mut sink = sink.lock().expect("Virtual output poisoned");
+            let result = if finished {
+                sink.flush()
+            } else {
+                sink.write(&block)
+            };
+
+            match result {
+                Ok(()) => !finished,
+                Err(err) => {
+                    error_callback(sink.output_err(err));
+                    false
+                }
+            }
+        });
+
+        Ok((Box::new(stream), sample_rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+
+    #[test]
+    fn played_audio_records_back() {
+        let path =
+            std::env::temp_dir().join(format!("tts-loop-virtual-{}.wav", std::process::id()));
+        let sample_rate = 16000;
+        // Whole blocks, so that nothing is padded
+        let played = (0..1600)
+            .map(|i| (i * 20 - 16000) as i16)
+            .collect::<Vec<_>>();
+
+        let output = VirtualBackend::new(VirtualAudio {
+            input: None,
+            output: Some(path.clone()),
+            speed: 1000.0,
+        })
+        .unwrap();
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let mut pos = 0;
+        let source = played.clone();
+        let (stream, stream_rate) = output
+            .output_stream(
+                None,
+                sample_rate,
+                Box::new(move |block: &mut [i16]| {
+                    if pos == source.len() {
+                        let _ = done_tx.send(());
+                        return true;
+                    }
+                    let end = (pos + block.len()).min(source.len());
+                    block[..end - pos].copy_from_slice(&source[pos..end]);
+                    pos = end;
+                    false
+                }),
+                Box::new(|e: Error| panic!("{}", e)),
+            )
+            .unwrap();
+        assert_eq!(stream_rate, sample_rate);
+        done_rx.recv().unwrap();
+        drop(stream);
+
+        let input = VirtualBackend::new(VirtualAudio {
+            input: Some(path.clone()),
+            output: None,
+            speed: 1000.0,
+        })
+        .unwrap();
+
+        let (samples_tx, samples_rx) = mpsc::channel();
+        let stream = input
+            .input_stream(
+                None,
+                8000,
+                Box::new(move |block: &[i16], rate| {
+                    let _ = samples_tx.send((block.to_vec(), rate));
+                }),
+                Box::new(|e: Error| panic!("{}", e)),
+            )
+            .unwrap();
+
+        let mut recorded = Vec::new();
+        while recorded.len() < played.len() + 160 {
+            let (block, rate) = samples_rx.recv().unwrap();
+            // Recorded at the rate of the file, not the one asked for
+            assert_eq!(rate, sample_rate);
+            recorded.extend(block);
+        }
+        drop(stream);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(&recorded[..played.len()], &played[..]);
+        // Followed by silence once the file ends
+        assert!(recorded[played.len()..].iter().all(|&s| s == 0));
+    }
+}
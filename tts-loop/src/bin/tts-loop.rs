@@ -1,5 +1,152 @@
+use clap::Parser;
+use tts_loop::{
+    DecoderConfig, Direction, HotWord, Options, Preprocessing, ResampleQuality, VadConfig,
+    VirtualAudio,
+};
+
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[clap(about = "Feeds text through tts and stt until it stops changing")]
+struct Args {
+    /// DeepSpeech model (.tflite or .pbmm). Defaults to $TTS_LOOP_MODEL, then
+    /// the config file
+    #[clap(long)]
+    model: Option<PathBuf>,
+    /// libdeepspeech.so, or a directory containing it. Only used when built
+    /// with the runtime-deepspeech feature. Can be repeated
+    #[clap(long = "deepspeech-lib")]
+    library_paths: Vec<PathBuf>,
+    /// Sample rate conversion quality: fast, medium or high. Defaults to the
+    /// config file, then medium
+    #[clap(long)]
+    resample_quality: Option<ResampleQuality>,
+    /// Longest recording in seconds, 0 for no limit. Defaults to the config
+    /// file, then 300
+    #[clap(long)]
+    max_recording_secs: Option<f32>,
+    /// Steps applied to synthesized audio before the loop recognizes it, comma
+    /// separated from dc, denoise, trim, pad=<secs>, peak and loudness.
+    /// Defaults to the config file, then none
+    #[clap(long)]
+    loop_preprocessing: Option<Preprocessing>,
+    /// Steps applied to recordings before they are transcribed, like
+    /// --loop-preprocessing
+    #[clap(long)]
+    recording_preprocessing: Option<Preprocessing>,
+    /// Listen for speech whenever idle, record it and transcribe it once it
+    /// ends
+    #[clap(long)]
+    voice_activation: bool,
+    /// Voice activation sensitivity from 0 to 1. Defaults to the config file,
+    /// then 0.5
+    #[clap(long)]
+    vad_sensitivity: Option<f32>,
+    /// Seconds of silence that end voice activated recordings. Defaults to the
+    /// config file, then 1
+    #[clap(long)]
+    vad_hangover_secs: Option<f32>,
+    /// Loop iterations run on each voice activated transcript, 0 to only
+    /// transcribe. Defaults to the config file, then 0
+    #[clap(long)]
+    vad_loop_iters: Option<u32>,
+    /// Recording device, as printed by --list-devices. Defaults to the device
+    /// last chosen in the GUI
+    #[clap(long)]
+    input_device: Option<String>,
+    /// Playback device, as printed by --list-devices. Defaults to the device
+    /// last chosen in the GUI
+    #[clap(long)]
+    output_device: Option<String>,
+    /// Print the available audio devices and exit
+    #[clap(long)]
+    list_devices: bool,
+    /// Use a virtual sound card instead of the audio devices. It records
+    /// silence and discards what is played unless given files
+    #[clap(long)]
+    virtual_audio: bool,
+    /// WAV file the virtual sound card records. Implies --virtual-audio
+    #[clap(long)]
+    virtual_input: Option<PathBuf>,
+    /// WAV file the virtual sound card plays to. Implies --virtual-audio
+    #[clap(long)]
+    virtual_output: Option<PathBuf>,
+    /// Speed of the virtual sound card relative to real time
+    #[clap(long, default_value = "1")]
+    virtual_speed: f64,
+    /// External scorer (.scorer) used by the speech recognizer
+    #[clap(long)]
+    scorer: Option<PathBuf>,
+    /// Language model weight, requires a scorer
+    #[clap(long, requires = "beta")]
+    alpha: Option<f32>,
+    /// Word insertion weight, requires a scorer
+    #[clap(long, requires = "alpha")]
+    beta: Option<f32>,
+    /// Beam width used by the speech recognizer's decoder
+    #[clap(long)]
+    beam_width: Option<u16>,
+    /// Word to bias recognition towards, as word:boost. Can be repeated
+    #[clap(long = "hot-word")]
+    hot_words: Vec<HotWord>,
+}
+
 fn main() {
-    let mut tts_looper = tts_loop::TtsLooper::new().expect("Failed to construct looper");
+    let args = Args::parse();
+    if args.list_devices {
+        for direction in [Direction::Input, Direction::Output] {
+            println!("{} devices:", direction);
+            for name in tts_loop::device_names(direction) {
+                println!("  {}", name);
+            }
+        }
+        return;
+    }
+
+    let virtual_audio =
+        if args.virtual_audio || args.virtual_input.is_some() || args.virtual_output.is_some() {
+            Some(VirtualAudio {
+                input: args.virtual_input,
+                output: args.virtual_output,
+                speed: args.virtual_speed,
+            })
+        } else {
+            None
+        };
+
+    let options = Options {
+        model: args.model,
+        library_paths: args.library_paths,
+        resample_quality: args.resample_quality,
+        max_recording_secs: args.max_recording_secs,
+        loop_preprocessing: args.loop_preprocessing,
+        recording_preprocessing: args.recording_preprocessing,
+        input_device: args.input_device,
+        output_device: args.output_device,
+        virtual_audio,
+        decoder: DecoderConfig {
+            scorer: args.scorer,
+            alpha: args.alpha,
+            beta: args.beta,
+            beam_width: args.beam_width,
+        },
+        vad: VadConfig {
+            // Leaves the config file's choice alone unless given
+            enabled: Some(true).filter(|_| args.voice_activation),
+            sensitivity: args.vad_sensitivity,
+            hangover_secs: args.vad_hangover_secs,
+            loop_iters: args.vad_loop_iters,
+        },
+        hot_words: args.hot_words,
+    };
+
+    let mut tts_looper = match tts_loop::TtsLooper::new(options) {
+        Ok(tts_looper) => tts_looper,
+        Err(e) => {
+            eprintln!("Failed to start: {}", e);
+            std::process::exit(1);
+        }
+    };
     tts_loop::init_logger(&tts_looper);
     tts_looper.run();
 }
@@ -0,0 +1,242 @@
+use crate::{
+    audio::VirtualAudio, hot_words::HotWord, preprocess::Preprocessing, resample::ResampleQuality,
+    vad::VadConfig,
+};
+
+use deepspeech::{errors::DeepspeechError, Model as DsModel};
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Environment variable that overrides the model path from the config file
+pub const MODEL_ENV_VAR: &str = "TTS_LOOP_MODEL";
+
+/// Recordings stop by themselves after this many seconds unless configured
+/// otherwise
+pub const DEFAULT_MAX_RECORDING_SECS: f32 = 300.0;
+
+/// Environment variable with extra libdeepspeech search paths, separated like
+/// `PATH`
+pub const LIBRARY_PATH_ENV_VAR: &str = "TTS_LOOP_DEEPSPEECH_PATH";
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Failed to read config {path}: {err}")]
+    Read { path: PathBuf, err: io::Error },
+    #[error("Invalid config {path}: {err}")]
+    Parse { path: PathBuf, err: toml::de::Error },
+}
+
+/// `$XDG_CONFIG_HOME/tts-loop`, falling back to `~/.config/tts-loop`
+pub(crate) fn config_dir() -> PathBuf {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .unwrap_or_default();
+
+    config_dir.join("tts-loop")
+}
+
+/// Model shipped in the source tree, only found when running from a checkout
+fn default_model_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("res/deepspeech-0.9.3-models.tflite")
+}
+
+/// DeepSpeech decoder settings. Unset values use the model/scorer defaults
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DecoderConfig {
+    /// External scorer (.scorer) used to rescore beam search results
+    pub scorer: Option<PathBuf>,
+    /// Language model weight, only used with a scorer
+    pub alpha: Option<f32>,
+    /// Word insertion weight, only used with a scorer
+    pub beta: Option<f32>,
+    pub beam_width: Option<u16>,
+}
+
+impl DecoderConfig {
+    /// Configures model, resetting unset values to their defaults.
+    /// default_beam_width is the one the model was loaded with
+    pub(crate) fn apply(
+        &self,
+        model: &mut DsModel,
+        default_beam_width: u16,
+    ) -> Result<(), DeepspeechError> {
+        match &self.scorer {
+            Some(scorer) => {
+                // Loading the scorer again also restores its default alpha and
+                // beta
+                model.enable_external_scorer(scorer)?;
+                if let (Some(alpha), Some(beta)) = (self.alpha, self.beta) {
+                    model.set_scorer_alpha_beta(alpha, beta)?;
+                }
+            }
+            None => {
+                // Fails if no scorer was enabled, which is the state we want
+                let _ = model.disable_external_scorer();
+            }
+        }
+
+        model.set_model_beam_width(self.beam_width.unwrap_or(default_beam_width))?;
+
+        Ok(())
+    }
+
+    /// Copy of self as the decoder uses it: with the beam width of the model
+    /// filled in, and alpha and beta only while a scorer uses them. Unset alpha
+    /// and beta are the scorer defaults
+    pub(crate) fn effective(&self, model: &DsModel) -> DecoderConfig {
+        let (alpha, beta) = match (&self.scorer, self.alpha, self.beta) {
+            (Some(_), Some(alpha), Some(beta)) => (Some(alpha), Some(beta)),
+            _ => (None, None),
+        };
+
+        DecoderConfig {
+            scorer: self.scorer.clone(),
+            alpha,
+            beta,
+            beam_width: Some(model.get_model_beam_width()),
+        }
+    }
+
+    /// Fills in every value not set in self from other
+    fn or(self, other: DecoderConfig) -> DecoderConfig {
+        DecoderConfig {
+            scorer: self.scorer.or(other.scorer),
+            alpha: self.alpha.or(other.alpha),
+            beta: self.beta.or(other.beta),
+            beam_width: self.beam_width.or(other.beam_width),
+        }
+    }
+}
+
+/// Contents of `config.toml` in the config dir. Relative paths are relative to
+/// the config dir
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    model: Option<PathBuf>,
+    library_paths: Vec<PathBuf>,
+    resample_quality: Option<ResampleQuality>,
+    max_recording_secs: Option<f32>,
+    loop_preprocessing: Option<Preprocessing>,
+    recording_preprocessing: Option<Preprocessing>,
+    decoder: DecoderConfig,
+    vad: VadConfig,
+}
+
+impl ConfigFile {
+    /// Loads the config at path. A missing file is treated as an empty config
+    fn load(path: &Path) -> Result<ConfigFile, Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+            Err(err) => {
+                return Err(Error::Read {
+                    path: path.to_path_buf(),
+                    err,
+                })
+            }
+        };
+
+        let mut config: ConfigFile = toml::from_str(&content).map_err(|err| Error::Parse {
+            path: path.to_path_buf(),
+            err,
+        })?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        config.model = config.model.map(|p| dir.join(p));
+        config.library_paths = config.library_paths.iter().map(|p| dir.join(p)).collect();
+        config.decoder.scorer = config.decoder.scorer.map(|p| dir.join(p));
+        Ok(config)
+    }
+}
+
+/// Startup options, usually filled in from the command line
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// DeepSpeech model. Falls back to MODEL_ENV_VAR, then the config file
+    pub model: Option<PathBuf>,
+    /// Files or directories searched for libdeepspeech when it is loaded at
+    /// runtime. Searched before LIBRARY_PATH_ENV_VAR and the config file
+    pub library_paths: Vec<PathBuf>,
+    /// Used to convert between voice, model and device sample rates
+    pub resample_quality: Option<ResampleQuality>,
+    /// Longest recording in seconds, 0 for no limit. Defaults to
+    /// DEFAULT_MAX_RECORDING_SECS
+    pub max_recording_secs: Option<f32>,
+    /// Applied to synthesized audio before the loop recognizes it. Defaults to
+    /// the config file, then no preprocessing
+    pub loop_preprocessing: Option<Preprocessing>,
+    /// Applied to recordings before they are transcribed. Defaults to the
+    /// config file, then no preprocessing
+    pub recording_preprocessing: Option<Preprocessing>,
+    /// Audio devices as listed by [`crate::device_names`]. Override the
+    /// devices last chosen in the GUI for this run
+    pub input_device: Option<String>,
+    pub output_device: Option<String>,
+    /// Simulated sound card used instead of the audio devices
+    pub virtual_audio: Option<VirtualAudio>,
+    pub decoder: DecoderConfig,
+    /// Voice activated recording
+    pub vad: VadConfig,
+    /// Used on top of the stored hot words for this run only
+    pub hot_words: Vec<HotWord>,
+}
+
+impl Options {
+    /// Fills in anything not given on the command line from the environment
+    /// and the config file
+    pub(crate) fn resolve(self) -> Result<Options, Error> {
+        let file = ConfigFile::load(&config_dir().join("config.toml"))?;
+
+        let model = self
+            .model
+            .or_else(|| std::env::var_os(MODEL_ENV_VAR).map(PathBuf::from))
+            .or(file.model);
+
+        let mut library_paths = self.library_paths;
+        if let Some(paths) = std::env::var_os(LIBRARY_PATH_ENV_VAR) {
+            library_paths.extend(std::env::split_paths(&paths));
+        }
+        library_paths.extend(file.library_paths);
+
+        Ok(Options {
+            model,
+            library_paths,
+            resample_quality: self.resample_quality.or(file.resample_quality),
+            max_recording_secs: self.max_recording_secs.or(file.max_recording_secs),
+            loop_preprocessing: self.loop_preprocessing.or(file.loop_preprocessing),
+            recording_preprocessing: self
+                .recording_preprocessing
+                .or(file.recording_preprocessing),
+            input_device: self.input_device,
+            output_device: self.output_device,
+            virtual_audio: self.virtual_audio,
+            decoder: self.decoder.or(file.decoder),
+            vad: self.vad.or(file.vad),
+            hot_words: self.hot_words,
+        })
+    }
+
+    pub(crate) fn max_recording(&self) -> Option<Duration> {
+        let secs = self
+            .max_recording_secs
+            .unwrap_or(DEFAULT_MAX_RECORDING_SECS);
+        if secs.is_finite() && secs > 0.0 {
+            Some(Duration::from_secs_f32(secs))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn model_path(&self) -> PathBuf {
+        self.model.clone().unwrap_or_else(default_model_path)
+    }
+}
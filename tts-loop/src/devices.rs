@@ -0,0 +1,163 @@
+use crate::config;
+
+use cpal::traits::*;
+use cpal::Device;
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+
+use std::{fmt, fs, io, path::PathBuf};
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("No default {0} device available")]
+    NoDefaultDevice(Direction),
+    #[error("{direction} device \"{name}\" not found. Available: {available}")]
+    NotFound {
+        direction: Direction,
+        name: String,
+        available: String,
+    },
+    #[error("Failed to read device selection {path}: {err}")]
+    Read { path: PathBuf, err: io::Error },
+    #[error("Invalid device selection {path}: {err}")]
+    Parse { path: PathBuf, err: toml::de::Error },
+    #[error("Failed to write device selection {path}: {err}")]
+    Write { path: PathBuf, err: io::Error },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Input => write!(f, "input"),
+            Direction::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// Devices of every available host, named `Host: device` so that devices with
+/// the same name on different hosts can be told apart
+fn devices(direction: Direction) -> Vec<(String, Device)> {
+    let mut ret = Vec::new();
+
+    for host_id in cpal::available_hosts() {
+        let host = match cpal::host_from_id(host_id) {
+            Ok(host) => host,
+            Err(_) => continue,
+        };
+
+        let devices = match direction {
+            Direction::Input => host.input_devices().map(|d| d.collect::<Vec<_>>()),
+            Direction::Output => host.output_devices().map(|d| d.collect::<Vec<_>>()),
+        };
+
+        for device in devices.unwrap_or_default() {
+            if let Ok(name) = device.name() {
+                ret.push((format!("{}: {}", host_id.name(), name), device));
+            }
+        }
+    }
+
+    ret
+}
+
+/// Names of the devices that can be selected
+pub fn device_names(direction: Direction) -> Vec<String> {
+    devices(direction)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// The device called name, or the default device of the default host if name
+/// is None
+pub(crate) fn find(direction: Direction, name: Option<&str>) -> Result<Device, Error> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let host = cpal::default_host();
+            let device = match direction {
+                Direction::Input => host.default_input_device(),
+                Direction::Output => host.default_output_device(),
+            };
+            return device.ok_or(Error::NoDefaultDevice(direction));
+        }
+    };
+
+    let mut devices = devices(direction);
+    match devices.iter().position(|(n, _)| n == name) {
+        Some(idx) => Ok(devices.swap_remove(idx).1),
+        None => {
+            let available = devices
+                .iter()
+                .map(|(n, _)| format!("\"{}\"", n))
+                .collect::<Vec<_>>();
+            Err(Error::NotFound {
+                direction,
+                name: name.to_string(),
+                available: available.join(", "),
+            })
+        }
+    }
+}
+
+pub(crate) fn default_path() -> PathBuf {
+    config::config_dir().join("devices.toml")
+}
+
+/// Devices chosen in the GUI, remembered across runs. None is the system
+/// default
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct DeviceSelection {
+    #[serde(skip)]
+    path: PathBuf,
+    pub(crate) input: Option<String>,
+    pub(crate) output: Option<String>,
+}
+
+impl DeviceSelection {
+    /// Loads the selection at path. A missing file selects the defaults
+    pub(crate) fn load(path: PathBuf) -> Result<DeviceSelection, Error> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::Read { path, err }),
+        };
+
+        let mut selection: DeviceSelection = match toml::from_str(&content) {
+            Ok(selection) => selection,
+            Err(err) => return Err(Error::Parse { path, err }),
+        };
+        selection.path = path;
+        Ok(selection)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        let write = || -> io::Result<()> {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let content = toml::to_string(self).expect("Device selection is always valid toml");
+            fs::write(&self.path, content)
+        };
+
+        write().map_err(|err| Error::Write {
+            path: self.path.clone(),
+            err,
+        })
+    }
+
+    pub(crate) fn set(&mut self, direction: Direction, name: Option<String>) {
+        match direction {
+            Direction::Input => self.input = name,
+            Direction::Output => self.output = name,
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    config::DecoderConfig,
+    hot_words::HotWord,
+    model::ModelInfo,
+    recognition,
+    resample::ResampleQuality,
+    scoring::{Reference, Scores, ScoringOptions},
+};
+
+use deepspeech::{errors::DeepspeechError, Model as DsModel};
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use std::{collections::VecDeque, ffi::NulError, fs::File, io, path::Path};
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    DeepspeechError(#[from] DeepspeechError),
+    #[error("Invalid C string")]
+    NulError(#[from] NulError),
+    #[error(transparent)]
+    Flite(#[from] flite::Error),
+}
+
+/// Limits on how far an exploration grows
+#[derive(Serialize, Clone, Copy, Debug)]
+pub(crate) struct Budget {
+    /// Number of candidate transcripts followed from every node
+    pub(crate) branching: usize,
+    /// Number of tts/stt round trips below the input
+    pub(crate) max_depth: usize,
+    /// Total number of nodes in the tree, including the input
+    pub(crate) max_nodes: usize,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Node {
+    pub(crate) text: String,
+    pub(crate) depth: usize,
+    pub(crate) parent: Option<usize>,
+    pub(crate) children: Vec<usize>,
+    /// Probability of this transcript among the candidates of its parent
+    pub(crate) probability: f32,
+    /// Product of the probabilities from the input down to this node
+    pub(crate) path_probability: f32,
+    pub(crate) scores: Scores,
+    /// Same text as an ancestor, expanding it would repeat that subtree
+    pub(crate) repeat: bool,
+}
+
+/// Every transcript reached while exploring, indexed by position. Node 0 is
+/// the input text
+#[derive(Serialize)]
+pub(crate) struct ExplorationTree {
+    pub(crate) input: String,
+    pub(crate) voice: String,
+    pub(crate) scoring: ScoringOptions,
+    pub(crate) model: ModelInfo,
+    pub(crate) decoder: DecoderConfig,
+    /// Hot words in effect, none without a scorer
+    pub(crate) hot_words: Vec<HotWord>,
+    pub(crate) budget: Budget,
+    pub(crate) nodes: Vec<Node>,
+}
+
+impl ExplorationTree {
+    /// Number of different texts the exploration ended on
+    pub(crate) fn distinct_leaves(&self) -> usize {
+        let mut leaves = self
+            .nodes
+            .iter()
+            .filter(|n| n.children.is_empty())
+            .map(|n| n.text.as_str())
+            .collect::<Vec<_>>();
+        leaves.sort_unstable();
+        leaves.dedup();
+        leaves.len()
+    }
+
+    /// Node ids in depth first order, the order the tree is displayed in
+    pub(crate) fn depth_first(&self) -> Vec<usize> {
+        let mut ret = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            ret.push(id);
+            stack.extend(self.nodes[id].children.iter().rev());
+        }
+        ret
+    }
+
+    pub(crate) fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let f = File::create(path)?;
+        serde_json::to_writer_pretty(f, self)?;
+        Ok(())
+    }
+
+    fn is_repeat(&self, parent: usize, text: &str) -> bool {
+        let mut ancestor = Some(parent);
+        while let Some(id) = ancestor {
+            if self.nodes[id].text == text {
+                return true;
+            }
+            ancestor = self.nodes[id].parent;
+        }
+        false
+    }
+}
+
+/// Breadth first exploration of the candidate transcripts of every loop
+/// iteration, expanded one node at a time so it can be interleaved with gui
+/// requests
+pub(crate) struct Exploration {
+    tree: ExplorationTree,
+    reference: Reference,
+    queue: VecDeque<usize>,
+}
+
+impl Exploration {
+    pub(crate) fn new(tree: ExplorationTree, reference: Reference) -> Result<Exploration, Error> {
+        let mut exploration = Exploration {
+            tree,
+            reference,
+            queue: VecDeque::new(),
+        };
+
+        let root = Node {
+            text: exploration.tree.input.clone(),
+            depth: 0,
+            parent: None,
+            children: Vec::new(),
+            probability: 1.0,
+            path_probability: 1.0,
+            scores: exploration
+                .reference
+                .score(&exploration.tree.input, &exploration.tree.voice)?,
+            repeat: false,
+        };
+        let expand = exploration.should_expand(&root);
+        exploration.tree.nodes.push(root);
+        if expand {
+            exploration.queue.push_back(0);
+        }
+
+        Ok(exploration)
+    }
+
+    pub(crate) fn tree(&self) -> &ExplorationTree {
+        &self.tree
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Stops expanding nodes, keeping the tree built so far
+    pub(crate) fn cancel(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Synthesizes and recognizes the next queued node, adding its distinct
+    /// top candidates as children
+    pub(crate) fn step(
+        &mut self,
+        model: &mut DsModel,
+        resample_quality: ResampleQuality,
+    ) -> Result<(), Error> {
+        let id = match self.queue.pop_front() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let wav = crate::synthesize(
+            self.tree.nodes[id].text.clone(),
+            self.tree.voice.clone(),
+            model.get_sample_rate() as u32,
+            resample_quality,
+        )?;
+        let recognition = recognition::recognize(model, &wav)?;
+
+        let mut seen = Vec::new();
+        for candidate in &recognition.candidates {
+            if seen.len() == self.tree.budget.branching
+                || self.tree.nodes.len() >= self.tree.budget.max_nodes
+            {
+                break;
+            }
+
+            if seen.contains(&candidate.text) {
+                continue;
+            }
+            seen.push(candidate.text.clone());
+
+            let parent = &self.tree.nodes[id];
+            let node = Node {
+                text: candidate.text.clone(),
+                depth: parent.depth + 1,
+                parent: Some(id),
+                children: Vec::new(),
+                probability: candidate.probability,
+                path_probability: parent.path_probability * candidate.probability,
+                scores: self.reference.score(&candidate.text, &self.tree.voice)?,
+                repeat: self.tree.is_repeat(id, &candidate.text),
+            };
+
+            let expand = self.should_expand(&node);
+            let child = self.tree.nodes.len();
+            self.tree.nodes.push(node);
+            self.tree.nodes[id].children.push(child);
+
+            if expand {
+                self.queue.push_back(child);
+            }
+        }
+
+        if self.tree.nodes.len() >= self.tree.budget.max_nodes {
+            self.queue.clear();
+        }
+
+        Ok(())
+    }
+
+    fn should_expand(&self, node: &Node) -> bool {
+        !node.repeat
+            && !node.text.is_empty()
+            && node.depth < self.tree.budget.max_depth
+            && self.tree.nodes.len() < self.tree.budget.max_nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exploration(max_depth: usize) -> Exploration {
+        let tree = ExplorationTree {
+            input: "hello world".to_string(),
+            voice: "kal".to_string(),
+            scoring: ScoringOptions::default(),
+            model: ModelInfo::default(),
+            decoder: DecoderConfig::default(),
+            hot_words: Vec::new(),
+            budget: Budget {
+                branching: 3,
+                max_depth,
+                max_nodes: 100,
+            },
+            nodes: Vec::new(),
+        };
+        Exploration::new(tree, Reference::default()).unwrap()
+    }
+
+    #[test]
+    fn depth_zero_does_not_expand_input() {
+        let exploration = exploration(0);
+        assert!(exploration.is_finished());
+        assert_eq!(exploration.tree().nodes.len(), 1);
+        assert_eq!(exploration.tree().nodes[0].text, "hello world");
+    }
+
+    #[test]
+    fn input_is_queued_below_max_depth() {
+        assert!(!exploration(1).is_finished());
+    }
+}
@@ -56,16 +56,24 @@ class OutputModel : public QAbstractListModel {
       return row >= low && row <= high;
     }
 
+    if (role == kTooltipsRole) {
+      return tooltips_[index.row()];
+    }
+
     return QVariant();
   }
 
   QHash<int, QByteArray> roleNames() const override {
-    return {{Qt::DisplayRole, "display"}, {Qt::UserRole, "selected"}};
+    return {{Qt::DisplayRole, "display"},
+            {Qt::UserRole, "selected"},
+            {kTooltipsRole, "tooltips"}};
   }
 
-  void addOutput(const QString& output) {
+  void addOutput(const QString& output,
+                 const QStringList& tooltips = QStringList()) {
     emit beginInsertRows(QModelIndex(), 0, 0);
     data_.push_front(output);
+    tooltips_.push_front(tooltips);
     if (selection_start_ >= 0) {
       selection_start_ += 1;
     }
@@ -139,7 +147,11 @@ class OutputModel : public QAbstractListModel {
   }
 
  private:
+  // Tooltips of the links in a row, indexed by their href
+  static constexpr int kTooltipsRole = Qt::UserRole + 1;
+
   QStringList data_;
+  QList<QStringList> tooltips_;
   int selection_start_ = -1;
   int selection_end_ = -1;
 };
@@ -149,6 +161,28 @@ class Backend : public QObject {
 
   Q_PROPERTY(QAbstractItemModel* output READ Output NOTIFY OutputChanged)
   Q_PROPERTY(QStringList voices MEMBER voices_ NOTIFY VoicesChanged)
+  Q_PROPERTY(QVariantList lexicon MEMBER lexicon_ NOTIFY LexiconChanged)
+  Q_PROPERTY(QVariantList hotWords MEMBER hot_words_ NOTIFY HotWordsChanged)
+  Q_PROPERTY(QVariantList takes MEMBER takes_ NOTIFY TakesChanged)
+  Q_PROPERTY(QVariantList explorationTree MEMBER exploration_tree_ NOTIFY
+                 ExplorationTreeChanged)
+  Q_PROPERTY(QString intermediateText MEMBER intermediate_text_ NOTIFY
+                 IntermediateTextChanged)
+  Q_PROPERTY(QString normalizedText MEMBER normalized_text_ NOTIFY
+                 NormalizedTextChanged)
+  Q_PROPERTY(QString modelPath MEMBER model_path_ NOTIFY ModelPathChanged)
+  Q_PROPERTY(QString resampleQuality MEMBER resample_quality_ NOTIFY
+                 ResampleQualityChanged)
+  Q_PROPERTY(QVariantMap decoderConfig MEMBER decoder_config_ NOTIFY
+                 DecoderConfigChanged)
+  Q_PROPERTY(QVariantMap audioDevices MEMBER audio_devices_ NOTIFY
+                 AudioDevicesChanged)
+  Q_PROPERTY(QVariantMap recordingState MEMBER recording_state_ NOTIFY
+                 RecordingStateChanged)
+  Q_PROPERTY(QVariantMap vadConfig MEMBER vad_config_ NOTIFY VadConfigChanged)
+  // Preprocessing settings keyed by target
+  Q_PROPERTY(QVariantMap preprocessing MEMBER preprocessing_ NOTIFY
+                 PreprocessingChanged)
 
  public:
   Backend(GuiCallbacks callbacks, QStringList voices, const void* data)
@@ -173,6 +207,96 @@ class Backend : public QObject {
     emit InputText(text);
   }
 
+  void SetModelPath(const QString& path) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetModelPath(path); });
+      return;
+    }
+
+    model_path_ = path;
+    emit ModelPathChanged();
+  }
+
+  void SetResampleQuality(const QString& quality) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetResampleQuality(quality); });
+      return;
+    }
+
+    resample_quality_ = quality;
+    emit ResampleQualityChanged();
+  }
+
+  void SetAudioDevices(const QVariantMap& devices) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetAudioDevices(devices); });
+      return;
+    }
+
+    audio_devices_ = devices;
+    emit AudioDevicesChanged();
+  }
+
+  void SetRecordingState(const QVariantMap& state) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetRecordingState(state); });
+      return;
+    }
+
+    recording_state_ = state;
+    emit RecordingStateChanged();
+  }
+
+  void SetDecoderConfig(const QVariantMap& config) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetDecoderConfig(config); });
+      return;
+    }
+
+    decoder_config_ = config;
+    emit DecoderConfigChanged();
+  }
+
+  void SetVadConfig(const QVariantMap& config) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetVadConfig(config); });
+      return;
+    }
+
+    vad_config_ = config;
+    emit VadConfigChanged();
+  }
+
+  void SetPreprocessing(const QString& target, const QVariantMap& config) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetPreprocessing(target, config); });
+      return;
+    }
+
+    preprocessing_[target] = config;
+    emit PreprocessingChanged();
+  }
+
+  void PushIntermediateText(const QString& text) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { PushIntermediateText(text); });
+      return;
+    }
+
+    intermediate_text_ = text;
+    emit IntermediateTextChanged();
+  }
+
+  void SetNormalizedText(const QString& text) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetNormalizedText(text); });
+      return;
+    }
+
+    normalized_text_ = text;
+    emit NormalizedTextChanged();
+  }
+
   void PushOutputRaw(const QString& text) {
     if (QThread::currentThread() != thread()) {
       QMetaObject::invokeMethod(this,
@@ -183,6 +307,55 @@ class Backend : public QObject {
     output_.addOutput(text);
   }
 
+  void PushTranscript(const QString& text, const QStringList& tooltips) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { PushTranscript(text, tooltips); });
+      return;
+    }
+
+    output_.addOutput(text, tooltips);
+  }
+
+  void SetLexicon(const QVariantList& lexicon) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetLexicon(lexicon); });
+      return;
+    }
+
+    lexicon_ = lexicon;
+    emit LexiconChanged();
+  }
+
+  void SetExplorationTree(const QVariantList& tree) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetExplorationTree(tree); });
+      return;
+    }
+
+    exploration_tree_ = tree;
+    emit ExplorationTreeChanged();
+  }
+
+  void SetHotWords(const QVariantList& hot_words) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetHotWords(hot_words); });
+      return;
+    }
+
+    hot_words_ = hot_words;
+    emit HotWordsChanged();
+  }
+
+  void SetTakes(const QVariantList& takes) {
+    if (QThread::currentThread() != thread()) {
+      QMetaObject::invokeMethod(this, [=] { SetTakes(takes); });
+      return;
+    }
+
+    takes_ = takes;
+    emit TakesChanged();
+  }
+
  public slots:
   void RunLoop(const QString& text, int num_iters) {
     callbacks_.start_tts_loop(QStringToGuiString(text).s, num_iters, data_);
@@ -194,6 +367,60 @@ class Backend : public QObject {
 
   void EnableAudio(bool enable) { callbacks_.enable_audio(enable, data_); }
 
+  void EnablePhonemeScoring(bool enable) {
+    callbacks_.enable_phoneme_scoring(enable, data_);
+  }
+
+  void ApplyResampleQuality(const QString& quality) {
+    callbacks_.set_resample_quality(QStringToGuiString(quality).s, data_);
+  }
+
+  void SetInputDevice(const QString& name) {
+    callbacks_.set_input_device(QStringToGuiString(name).s, data_);
+  }
+
+  void SetOutputDevice(const QString& name) {
+    callbacks_.set_output_device(QStringToGuiString(name).s, data_);
+  }
+
+  void SetModel(const QUrl& path) {
+    callbacks_.set_model(QStringToGuiString(path.toLocalFile()).s, data_);
+  }
+
+  void ApplyDecoderConfig(const QUrl& scorer, const QString& alpha,
+                          const QString& beta, int beam_width) {
+    callbacks_.set_decoder_config(QStringToGuiString(scorer.toLocalFile()).s,
+                                  QStringToGuiString(alpha).s,
+                                  QStringToGuiString(beta).s, beam_width,
+                                  data_);
+  }
+
+  void ApplyVadConfig(bool enabled, double sensitivity, double hangover_secs,
+                      int loop_iters) {
+    callbacks_.set_vad_config(enabled, sensitivity, hangover_secs, loop_iters,
+                              data_);
+  }
+
+  void ApplyPreprocessing(const QString& target, bool remove_dc, bool denoise,
+                          bool trim, double pad_secs,
+                          const QString& normalize) {
+    callbacks_.set_preprocessing(QStringToGuiString(target).s, remove_dc,
+                                 denoise, trim, pad_secs,
+                                 QStringToGuiString(normalize).s, data_);
+  }
+
+  void EnableStreamingStt(bool enable) {
+    callbacks_.enable_streaming_stt(enable, data_);
+  }
+
+  void EnableNormalizedScoring(bool enable) {
+    callbacks_.enable_normalized_scoring(enable, data_);
+  }
+
+  void PreviewNormalization(const QString& text) {
+    callbacks_.preview_normalization(QStringToGuiString(text).s, data_);
+  }
+
   void Cancel() { callbacks_.cancel(data_); }
 
   void Copy() {
@@ -218,16 +445,102 @@ class Backend : public QObject {
     callbacks_.end_recording(data_);
   }
 
+  void PauseRecording() {
+    callbacks_.pause_recording(data_);
+  }
+
+  void ResumeRecording() {
+    callbacks_.resume_recording(data_);
+  }
+
+  void PlayTake(int id) { callbacks_.play_take(id, data_); }
+
+  void RenameTake(int id, const QString& name) {
+    callbacks_.rename_take(id, QStringToGuiString(name).s, data_);
+  }
+
+  void ExportTake(int id, const QUrl& path) {
+    callbacks_.export_take(id, QStringToGuiString(path.toLocalFile()).s,
+                           data_);
+  }
+
+  void TranscribeTake(int id) { callbacks_.transcribe_take(id, data_); }
+
+  void RemoveTake(int id) { callbacks_.remove_take(id, data_); }
+
+  void LoopTake(int id, int num_iters) {
+    callbacks_.loop_take(id, num_iters, data_);
+  }
+
+  void SetLexiconEntry(const QString& word, const QString& phones) {
+    callbacks_.set_lexicon_entry(QStringToGuiString(word).s,
+                                 QStringToGuiString(phones).s, data_);
+  }
+
+  void RemoveLexiconEntry(const QString& word) {
+    callbacks_.remove_lexicon_entry(QStringToGuiString(word).s, data_);
+  }
+
+  void PreviewWord(const QString& word) {
+    callbacks_.preview_word(QStringToGuiString(word).s, data_);
+  }
+
+  void SetHotWord(const QString& word, const QString& boost) {
+    callbacks_.set_hot_word(QStringToGuiString(word).s,
+                            QStringToGuiString(boost).s, data_);
+  }
+
+  void RemoveHotWord(const QString& word) {
+    callbacks_.remove_hot_word(QStringToGuiString(word).s, data_);
+  }
+
+  void Explore(const QString& text, int branching, int max_depth,
+               int max_nodes) {
+    callbacks_.explore(QStringToGuiString(text).s, branching, max_depth,
+                       max_nodes, data_);
+  }
+
+  void SaveExploration(const QUrl& path) {
+    callbacks_.save_exploration(QStringToGuiString(path.toLocalFile()).s,
+                                data_);
+  }
+
   QAbstractItemModel* Output() { return &output_; }
 
  signals:
   void OutputChanged();
   void VoicesChanged();
+  void LexiconChanged();
+  void HotWordsChanged();
+  void TakesChanged();
+  void ExplorationTreeChanged();
+  void IntermediateTextChanged();
+  void NormalizedTextChanged();
+  void ModelPathChanged();
+  void ResampleQualityChanged();
+  void DecoderConfigChanged();
+  void AudioDevicesChanged();
+  void RecordingStateChanged();
+  void VadConfigChanged();
+  void PreprocessingChanged();
   void InputText(QString text);
 
  private:
   GuiCallbacks callbacks_;
   QStringList voices_;
+  QVariantList lexicon_;
+  QVariantList hot_words_;
+  QVariantList takes_;
+  QVariantList exploration_tree_;
+  QString intermediate_text_;
+  QString normalized_text_;
+  QString model_path_;
+  QString resample_quality_;
+  QVariantMap decoder_config_;
+  QVariantMap audio_devices_;
+  QVariantMap recording_state_;
+  QVariantMap vad_config_;
+  QVariantMap preprocessing_;
   const void* data_;
   OutputModel output_;
 };
@@ -235,6 +548,17 @@ class Backend : public QObject {
 struct Gui {
   GuiCallbacks callbacks;
   QStringList voices;
+  // Values pushed before the backend was constructed
+  QVariantList lexicon;
+  QVariantList hot_words;
+  QVariantList takes;
+  QString model_path;
+  QString resample_quality;
+  QVariantMap decoder_config;
+  QVariantMap audio_devices;
+  QVariantMap recording_state;
+  QVariantMap vad_config;
+  QVariantMap preprocessing;
   Backend* backend = nullptr;
 
   Gui(GuiCallbacks callbacks, QStringList voices)
@@ -260,6 +584,19 @@ void Exec(Gui* gui, const void* data) {
   QQuickStyle::setStyle("Fusion");
 
   Backend backend(gui->callbacks, gui->voices, data);
+  backend.SetLexicon(gui->lexicon);
+  backend.SetHotWords(gui->hot_words);
+  backend.SetTakes(gui->takes);
+  backend.SetModelPath(gui->model_path);
+  backend.SetResampleQuality(gui->resample_quality);
+  backend.SetDecoderConfig(gui->decoder_config);
+  backend.SetAudioDevices(gui->audio_devices);
+  backend.SetRecordingState(gui->recording_state);
+  backend.SetVadConfig(gui->vad_config);
+  for (auto it = gui->preprocessing.begin(); it != gui->preprocessing.end();
+       ++it) {
+    backend.SetPreprocessing(it.key(), it.value().toMap());
+  }
   gui->backend = &backend;
 
   QQmlApplicationEngine engine;
@@ -284,10 +621,208 @@ void PushInputText(Gui* gui, String text) {
 
 }
 
+void SetLexicon(Gui* gui, const String* words, const String* phones,
+                uint64_t num_entries) {
+  QVariantList lexicon;
+  for (uint64_t i = 0; i < num_entries; ++i) {
+    QVariantMap entry;
+    entry["word"] = GuiStringToQString(words[i]);
+    entry["phones"] = GuiStringToQString(phones[i]);
+    lexicon.push_back(entry);
+  }
+
+  if (gui->backend) {
+    gui->backend->SetLexicon(lexicon);
+  } else {
+    gui->lexicon = lexicon;
+  }
+}
+
+void SetExplorationTree(Gui* gui, const uint32_t* depths, const String* texts,
+                        const String* details, uint64_t num_nodes) {
+  QVariantList tree;
+  for (uint64_t i = 0; i < num_nodes; ++i) {
+    QVariantMap node;
+    node["depth"] = depths[i];
+    node["text"] = GuiStringToQString(texts[i]);
+    node["details"] = GuiStringToQString(details[i]);
+    tree.push_back(node);
+  }
+
+  if (gui->backend) {
+    gui->backend->SetExplorationTree(tree);
+  }
+}
+
+void SetHotWords(Gui* gui, const String* words, const float* boosts,
+                 uint64_t num_entries) {
+  QVariantList hot_words;
+  for (uint64_t i = 0; i < num_entries; ++i) {
+    QVariantMap entry;
+    entry["word"] = GuiStringToQString(words[i]);
+    entry["boost"] = boosts[i];
+    hot_words.push_back(entry);
+  }
+
+  if (gui->backend) {
+    gui->backend->SetHotWords(hot_words);
+  } else {
+    gui->hot_words = hot_words;
+  }
+}
+
+void SetTakes(Gui* gui, const uint32_t* ids, const String* names,
+              const float* durations_secs, const String* texts,
+              uint64_t num_takes) {
+  QVariantList takes;
+  for (uint64_t i = 0; i < num_takes; ++i) {
+    QVariantMap take;
+    take["id"] = ids[i];
+    take["name"] = GuiStringToQString(names[i]);
+    take["duration"] = durations_secs[i];
+    take["text"] = GuiStringToQString(texts[i]);
+    takes.push_back(take);
+  }
+
+  if (gui->backend) {
+    gui->backend->SetTakes(takes);
+  } else {
+    gui->takes = takes;
+  }
+}
+
+void SetModelPath(Gui* gui, String path) {
+  if (gui->backend) {
+    gui->backend->SetModelPath(GuiStringToQString(path));
+  } else {
+    gui->model_path = GuiStringToQString(path);
+  }
+}
+
+void SetResampleQuality(Gui* gui, String quality) {
+  if (gui->backend) {
+    gui->backend->SetResampleQuality(GuiStringToQString(quality));
+  } else {
+    gui->resample_quality = GuiStringToQString(quality);
+  }
+}
+
+void SetAudioDevices(Gui* gui, const String* inputs, uint64_t num_inputs,
+                     const String* outputs, uint64_t num_outputs,
+                     String input, String output) {
+  QStringList input_names;
+  for (uint64_t i = 0; i < num_inputs; ++i) {
+    input_names.push_back(GuiStringToQString(inputs[i]));
+  }
+  QStringList output_names;
+  for (uint64_t i = 0; i < num_outputs; ++i) {
+    output_names.push_back(GuiStringToQString(outputs[i]));
+  }
+
+  QVariantMap devices;
+  devices["inputs"] = input_names;
+  devices["outputs"] = output_names;
+  devices["input"] = GuiStringToQString(input);
+  devices["output"] = GuiStringToQString(output);
+
+  if (gui->backend) {
+    gui->backend->SetAudioDevices(devices);
+  } else {
+    gui->audio_devices = devices;
+  }
+}
+
+void SetRecordingState(Gui* gui, String state, float elapsed_secs,
+                       float max_secs) {
+  QVariantMap recording_state;
+  recording_state["state"] = GuiStringToQString(state);
+  recording_state["elapsed"] = elapsed_secs;
+  recording_state["max"] = max_secs;
+
+  if (gui->backend) {
+    gui->backend->SetRecordingState(recording_state);
+  } else {
+    gui->recording_state = recording_state;
+  }
+}
+
+void SetDecoderConfig(Gui* gui, String scorer, String alpha, String beta,
+                      uint32_t beam_width) {
+  QVariantMap config;
+  auto scorer_path = GuiStringToQString(scorer);
+  config["scorer"] =
+      scorer_path.isEmpty() ? QUrl() : QUrl::fromLocalFile(scorer_path);
+  config["alpha"] = GuiStringToQString(alpha);
+  config["beta"] = GuiStringToQString(beta);
+  config["beamWidth"] = beam_width;
+
+  if (gui->backend) {
+    gui->backend->SetDecoderConfig(config);
+  } else {
+    gui->decoder_config = config;
+  }
+}
+
+void SetVadConfig(Gui* gui, bool enabled, float sensitivity,
+                  float hangover_secs, uint32_t loop_iters) {
+  QVariantMap config;
+  config["enabled"] = enabled;
+  config["sensitivity"] = sensitivity;
+  config["hangoverSecs"] = hangover_secs;
+  config["loopIters"] = loop_iters;
+
+  if (gui->backend) {
+    gui->backend->SetVadConfig(config);
+  } else {
+    gui->vad_config = config;
+  }
+}
+
+void SetPreprocessing(Gui* gui, String target, bool remove_dc, bool denoise,
+                      bool trim, float pad_secs, String normalize) {
+  QVariantMap config;
+  config["removeDc"] = remove_dc;
+  config["denoise"] = denoise;
+  config["trim"] = trim;
+  config["padSecs"] = pad_secs;
+  config["normalize"] = GuiStringToQString(normalize);
+
+  auto q_target = GuiStringToQString(target);
+  if (gui->backend) {
+    gui->backend->SetPreprocessing(q_target, config);
+  } else {
+    gui->preprocessing[q_target] = config;
+  }
+}
+
+void PushIntermediateText(Gui* gui, String text) {
+  if (gui->backend) {
+    gui->backend->PushIntermediateText(GuiStringToQString(text));
+  }
+}
+
+void SetNormalizedText(Gui* gui, String text) {
+  if (gui->backend) {
+    gui->backend->SetNormalizedText(GuiStringToQString(text));
+  }
+}
+
 void PushRawOutput(Gui* gui, String text) {
   if (gui->backend) {
     gui->backend->PushOutputRaw(GuiStringToQString(text));
   }
 }
 
+void PushTranscript(Gui* gui, String text, const String* tooltips,
+                    uint64_t num_tooltips) {
+  QStringList q_tooltips;
+  for (uint64_t i = 0; i < num_tooltips; ++i) {
+    q_tooltips.push_back(GuiStringToQString(tooltips[i]));
+  }
+
+  if (gui->backend) {
+    gui->backend->PushTranscript(GuiStringToQString(text), q_tooltips);
+  }
+}
+
 #include "gui.moc"
@@ -17,10 +17,47 @@ typedef struct GuiCallbacks {
   void (*start_tts_loop)(String text, int32_t num_iters, const void* data);
   void (*set_voice)(String voice, const void* data);
   void (*enable_audio)(bool enable, const void* data);
+  void (*enable_phoneme_scoring)(bool enable, const void* data);
+  void (*enable_normalized_scoring)(bool enable, const void* data);
+  void (*enable_streaming_stt)(bool enable, const void* data);
+  // Empty strings and a zero beam width mean "use the default"
+  void (*set_decoder_config)(String scorer, String alpha, String beta,
+                             uint32_t beam_width, const void* data);
+  // One of "fast", "medium" or "high"
+  void (*set_resample_quality)(String quality, const void* data);
+  // sensitivity is between 0 and 1. loop_iters of 0 only transcribes speech
+  void (*set_vad_config)(bool enabled, float sensitivity, float hangover_secs,
+                         uint32_t loop_iters, const void* data);
+  // target is "loop" or "recording". normalize is "peak", "loudness" or empty
+  void (*set_preprocessing)(String target, bool remove_dc, bool denoise,
+                            bool trim, float pad_secs, String normalize,
+                            const void* data);
+  // Device names as passed to SetAudioDevices, empty for the system default
+  void (*set_input_device)(String name, const void* data);
+  void (*set_output_device)(String name, const void* data);
+  void (*preview_normalization)(String text, const void* data);
   void (*cancel)(const void* data);
   void (*start_recording)(const void* data);
   void (*end_recording)(const void* data);
+  void (*pause_recording)(const void* data);
+  void (*resume_recording)(const void* data);
+  // Takes are identified by the ids passed to SetTakes
+  void (*play_take)(uint32_t id, const void* data);
+  void (*rename_take)(uint32_t id, String name, const void* data);
+  void (*export_take)(uint32_t id, String path, const void* data);
+  void (*transcribe_take)(uint32_t id, const void* data);
+  void (*remove_take)(uint32_t id, const void* data);
+  void (*loop_take)(uint32_t id, int32_t num_iters, const void* data);
   void (*save)(String path, const void* data);
+  void (*set_lexicon_entry)(String word, String phones, const void* data);
+  void (*remove_lexicon_entry)(String word, const void* data);
+  void (*preview_word)(String word, const void* data);
+  void (*set_hot_word)(String word, String boost, const void* data);
+  void (*remove_hot_word)(String word, const void* data);
+  void (*set_model)(String path, const void* data);
+  void (*explore)(String text, uint32_t branching, uint32_t max_depth,
+                  uint32_t max_nodes, const void* data);
+  void (*save_exploration)(String path, const void* data);
 } GuiCallbacks;
 
 Gui* MakeGui(GuiCallbacks callbacks, const String* voices, uint64_t num_voices);
@@ -28,7 +65,42 @@ void DestroyGui(Gui* gui);
 
 void PushOutput(Gui* gui, String text);
 void PushRawOutput(Gui* gui, String text);
+// Rich text output. Hovering over a link with href i shows the plain text
+// tooltips[i]
+void PushTranscript(Gui* gui, String text, const String* tooltips,
+                    uint64_t num_tooltips);
 void PushInputText(Gui* gui, String text);
+void PushIntermediateText(Gui* gui, String text);
+// Normalization preview of the input text
+void SetNormalizedText(Gui* gui, String text);
+// Nodes in depth first order
+void SetExplorationTree(Gui* gui, const uint32_t* depths, const String* texts,
+                        const String* details, uint64_t num_nodes);
+void SetModelPath(Gui* gui, String path);
+void SetResampleQuality(Gui* gui, String quality);
+// Empty selections mean the system default
+void SetAudioDevices(Gui* gui, const String* inputs, uint64_t num_inputs,
+                     const String* outputs, uint64_t num_outputs,
+                     String input, String output);
+// state is "idle", "listening", "recording" or "paused". A max of 0 means no
+// limit
+void SetRecordingState(Gui* gui, String state, float elapsed_secs,
+                       float max_secs);
+void SetDecoderConfig(Gui* gui, String scorer, String alpha, String beta,
+                      uint32_t beam_width);
+void SetVadConfig(Gui* gui, bool enabled, float sensitivity,
+                  float hangover_secs, uint32_t loop_iters);
+// Same values as the set_preprocessing callback
+void SetPreprocessing(Gui* gui, String target, bool remove_dc, bool denoise,
+                      bool trim, float pad_secs, String normalize);
+void SetLexicon(Gui* gui, const String* words, const String* phones,
+                uint64_t num_entries);
+void SetHotWords(Gui* gui, const String* words, const float* boosts,
+                 uint64_t num_entries);
+// texts are empty for takes that haven't been transcribed
+void SetTakes(Gui* gui, const uint32_t* ids, const String* names,
+              const float* durations_secs, const String* texts,
+              uint64_t num_takes);
 
 void Exec(Gui* gui, const void* data);
 
@@ -0,0 +1,110 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+import Qt.labs.platform 1.1
+
+Window {
+    title: qsTr("Decoder")
+    width: 480
+    height: 220
+    minimumWidth: 400
+    minimumHeight: 220
+
+    property url scorer: backend.decoderConfig.scorer || ""
+
+    GridLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+        columns: 2
+
+        Text {
+            text: qsTr("Scorer")
+        }
+
+        RowLayout {
+            Layout.fillWidth: true
+
+            Text {
+                Layout.fillWidth: true
+                text: scorer != "" ? scorer.toString().replace("file://", "") : qsTr("None")
+                elide: Text.ElideLeft
+            }
+
+            Button {
+                text: qsTr("Browse")
+
+                onClicked: {
+                    scorerDialog.open()
+                }
+
+                FileDialog {
+                    id: scorerDialog
+                    fileMode: FileDialog.OpenFile
+                    nameFilters: [ "Scorer files (*.scorer)"]
+
+                    onAccepted: {
+                        scorer = file
+                    }
+                }
+            }
+
+            Button {
+                text: qsTr("Clear")
+
+                onClicked: {
+                    scorer = ""
+                }
+            }
+        }
+
+        Text {
+            text: qsTr("Alpha")
+        }
+
+        TextField {
+            id: alpha
+            Layout.fillWidth: true
+            text: backend.decoderConfig.alpha || ""
+            placeholderText: qsTr("Scorer default")
+            validator: DoubleValidator {}
+        }
+
+        Text {
+            text: qsTr("Beta")
+        }
+
+        TextField {
+            id: beta
+            Layout.fillWidth: true
+            text: backend.decoderConfig.beta || ""
+            placeholderText: qsTr("Scorer default")
+            validator: DoubleValidator {}
+        }
+
+        Text {
+            text: qsTr("Beam width")
+        }
+
+        SpinBox {
+            id: beamWidth
+            from: 1
+            to: 65535
+            editable: true
+            value: backend.decoderConfig.beamWidth || 1
+        }
+
+        Item {
+            Layout.fillHeight: true
+        }
+
+        Button {
+            Layout.alignment: Qt.AlignRight
+            text: qsTr("Apply")
+
+            onClicked: {
+                backend.ApplyDecoderConfig(scorer, alpha.text, beta.text, beamWidth.value)
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+import Qt.labs.platform 1.1
+
+Window {
+    title: qsTr("Exploration")
+    width: 640
+    height: 480
+    minimumWidth: 480
+    minimumHeight: 240
+
+    property string sourceText: ""
+
+    ColumnLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+
+        RowLayout {
+            Text {
+                text: qsTr("Branching")
+            }
+
+            SpinBox {
+                id: branching
+                from: 1
+                to: 5
+                value: 2
+            }
+
+            Text {
+                text: qsTr("Depth")
+            }
+
+            SpinBox {
+                id: maxDepth
+                from: 1
+                to: 20
+                value: 3
+            }
+
+            Text {
+                text: qsTr("Nodes")
+            }
+
+            SpinBox {
+                id: maxNodes
+                from: 1
+                to: 1000
+                value: 50
+                editable: true
+            }
+        }
+
+        RowLayout {
+            Button {
+                text: qsTr("Explore")
+
+                onClicked: {
+                    backend.Explore(sourceText, branching.value, maxDepth.value, maxNodes.value)
+                }
+            }
+
+            Button {
+                text: qsTr("Cancel")
+
+                onClicked: {
+                    backend.Cancel()
+                }
+            }
+
+            Button {
+                text: qsTr("Export")
+
+                onClicked: {
+                    exportDialog.file = ""
+                    exportDialog.open()
+                }
+
+                FileDialog {
+                    id: exportDialog
+                    file: ""
+                    folder: StandardPaths.writableLocation(StandardPaths.DocumentsLocation)
+                    fileMode: FileDialog.SaveFile
+                    nameFilters: [ "Json files (*.json)"]
+
+                    onAccepted: {
+                        backend.SaveExploration(file)
+                    }
+                }
+            }
+        }
+
+        Rectangle {
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+            border.color: "lightgrey"
+
+            ListView {
+                id: nodes
+
+                anchors.fill: parent
+                anchors.margins: 2
+                clip: true
+
+                model: backend.explorationTree
+                delegate: RowLayout {
+                    width: nodes.width
+
+                    Text {
+                        Layout.fillWidth: true
+                        leftPadding: modelData.depth * 20
+                        text: modelData.text
+                        elide: Text.ElideRight
+                    }
+
+                    Text {
+                        text: modelData.details
+                        color: "grey"
+                    }
+                }
+
+                ScrollBar.vertical : ScrollBar {}
+            }
+        }
+    }
+}
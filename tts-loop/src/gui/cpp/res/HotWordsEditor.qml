@@ -0,0 +1,90 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+
+Window {
+    title: qsTr("Hot words")
+    width: 400
+    height: 360
+    minimumWidth: 360
+    minimumHeight: 240
+
+    ColumnLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+
+        Rectangle {
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+            border.color: "lightgrey"
+
+            ListView {
+                id: entries
+
+                anchors.fill: parent
+                anchors.margins: 2
+                clip: true
+
+                model: backend.hotWords
+                delegate: RowLayout {
+                    width: entries.width
+
+                    Text {
+                        Layout.fillWidth: true
+                        text: modelData.word
+                        font.bold: true
+                        elide: Text.ElideRight
+                    }
+
+                    Text {
+                        Layout.preferredWidth: 60
+                        text: modelData.boost
+                    }
+
+                    Button {
+                        text: qsTr("Edit")
+
+                        onClicked: {
+                            word.text = modelData.word
+                            boost.text = modelData.boost
+                        }
+                    }
+
+                    Button {
+                        text: qsTr("Remove")
+
+                        onClicked: {
+                            backend.RemoveHotWord(modelData.word)
+                        }
+                    }
+                }
+
+                ScrollBar.vertical : ScrollBar {}
+            }
+        }
+
+        RowLayout {
+            TextField {
+                id: word
+                Layout.fillWidth: true
+                placeholderText: qsTr("Word")
+            }
+
+            TextField {
+                id: boost
+                Layout.preferredWidth: 80
+                placeholderText: qsTr("Boost")
+                validator: DoubleValidator {}
+            }
+
+            Button {
+                text: qsTr("Set")
+
+                onClicked: {
+                    backend.SetHotWord(word.text, boost.text)
+                }
+            }
+        }
+    }
+}
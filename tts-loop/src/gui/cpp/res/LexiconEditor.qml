@@ -0,0 +1,106 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+
+Window {
+    title: qsTr("Lexicon")
+    width: 480
+    height: 360
+    minimumWidth: 480
+    minimumHeight: 240
+
+    ColumnLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+
+        Rectangle {
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+            border.color: "lightgrey"
+
+            ListView {
+                id: entries
+
+                anchors.fill: parent
+                anchors.margins: 2
+                clip: true
+
+                model: backend.lexicon
+                delegate: RowLayout {
+                    width: entries.width
+
+                    Text {
+                        Layout.preferredWidth: 120
+                        text: modelData.word
+                        font.bold: true
+                        elide: Text.ElideRight
+                    }
+
+                    Text {
+                        Layout.fillWidth: true
+                        text: modelData.phones
+                        elide: Text.ElideRight
+                    }
+
+                    Button {
+                        text: qsTr("Edit")
+
+                        onClicked: {
+                            word.text = modelData.word
+                            phones.text = modelData.phones
+                        }
+                    }
+
+                    Button {
+                        text: qsTr("Preview")
+
+                        onClicked: {
+                            backend.PreviewWord(modelData.word)
+                        }
+                    }
+
+                    Button {
+                        text: qsTr("Remove")
+
+                        onClicked: {
+                            backend.RemoveLexiconEntry(modelData.word)
+                        }
+                    }
+                }
+
+                ScrollBar.vertical : ScrollBar {}
+            }
+        }
+
+        RowLayout {
+            TextField {
+                id: word
+                Layout.preferredWidth: 120
+                placeholderText: qsTr("Word")
+            }
+
+            TextField {
+                id: phones
+                Layout.fillWidth: true
+                placeholderText: qsTr("Phones, e.g. t ah0 m ey1 t ow0")
+            }
+
+            Button {
+                text: qsTr("Set")
+
+                onClicked: {
+                    backend.SetLexiconEntry(word.text, phones.text)
+                }
+            }
+
+            Button {
+                text: qsTr("Preview")
+
+                onClicked: {
+                    backend.PreviewWord(word.text)
+                }
+            }
+        }
+    }
+}
@@ -13,6 +13,39 @@ ApplicationWindow {
     minimumWidth: 640
     minimumHeight: 480
 
+    // Device picker entries, the system default first. A selected device that
+    // is no longer connected stays listed so the selection remains visible
+    function deviceEntries(names, selected) {
+        var entries = [{ text: qsTr("Default"), name: "" }]
+        for (var i = 0; i < (names || []).length; ++i) {
+            entries.push({ text: names[i], name: names[i] })
+        }
+        if (selected && (names || []).indexOf(selected) < 0) {
+            entries.push({ text: qsTr("%1 (missing)").arg(selected), name: selected })
+        }
+        return entries
+    }
+
+    function deviceIndex(entries, selected) {
+        for (var i = 0; i < entries.length; ++i) {
+            if (entries[i].name === (selected || "")) {
+                return i
+            }
+        }
+        return 0
+    }
+
+    function recordingState() {
+        return (backend.recordingState || {}).state || "idle"
+    }
+
+    // m:ss
+    function formatDuration(secs) {
+        var total = Math.floor(secs)
+        var s = total % 60
+        return Math.floor(total / 60) + ":" + (s < 10 ? "0" : "") + s
+    }
+
     Rectangle {
         id: root
         anchors.fill: parent
@@ -58,12 +91,25 @@ ApplicationWindow {
                                     inputText.text = s
                                 }
 
+                                // The preview is of the previous text
+                                onTextChanged: {
+                                    backend.normalizedText = ""
+                                }
+
                                 verticalAlignment: TextInput.AlignTop
                                 placeholderText: qsTr("Tts text")
                                 wrapMode: TextInput.Wrap
                             }
                         }
                     }
+
+                    Text {
+                        Layout.fillWidth: true
+                        visible: backend.normalizedText != ""
+                        text: qsTr("Normalized: ") + backend.normalizedText
+                        color: "grey"
+                        wrapMode: Text.Wrap
+                    }
                 }
 
                 ColumnLayout {
@@ -98,6 +144,51 @@ ApplicationWindow {
                             }
                         }
 
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Streaming recognition")
+                        }
+
+                        CheckBox {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.leftMargin: 0
+                            checkState: Qt.Unchecked
+
+                            onCheckStateChanged: {
+                                backend.EnableStreamingStt(checkState)
+                            }
+                        }
+
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Phoneme scoring")
+                        }
+
+                        CheckBox {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.leftMargin: 0
+                            checkState: Qt.Unchecked
+
+                            onCheckStateChanged: {
+                                backend.EnablePhonemeScoring(checkState)
+                            }
+                        }
+
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Normalized scoring")
+                        }
+
+                        CheckBox {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.leftMargin: 0
+                            checkState: Qt.Unchecked
+
+                            onCheckStateChanged: {
+                                backend.EnableNormalizedScoring(checkState)
+                            }
+                        }
+
                         Text {
                             Layout.alignment: Qt.AlignRight
                             text: qsTr("Voice")
@@ -113,6 +204,85 @@ ApplicationWindow {
                             }
                         }
 
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Model")
+                        }
+
+                        Button {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.maximumWidth: 200
+                            text: backend.modelPath.split("/").pop()
+
+                            ToolTip.visible: hovered
+                            ToolTip.text: backend.modelPath
+
+                            onClicked: {
+                                modelDialog.open()
+                            }
+
+                            FileDialog {
+                                id: modelDialog
+                                fileMode: FileDialog.OpenFile
+                                nameFilters: [ "DeepSpeech models (*.tflite *.pbmm)"]
+
+                                onAccepted: {
+                                    backend.SetModel(file)
+                                }
+                            }
+                        }
+
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Resampling")
+                        }
+
+                        ComboBox {
+                            Layout.alignment: Qt.AlignLeft
+                            model: ["fast", "medium", "high"]
+                            currentIndex: model.indexOf(backend.resampleQuality)
+
+                            onActivated: {
+                                backend.ApplyResampleQuality(currentText)
+                            }
+                        }
+
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Input device")
+                        }
+
+                        ComboBox {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.maximumWidth: 300
+                            textRole: "text"
+                            model: deviceEntries(backend.audioDevices.inputs,
+                                                 backend.audioDevices.input)
+                            currentIndex: deviceIndex(model, backend.audioDevices.input)
+
+                            onActivated: {
+                                backend.SetInputDevice(model[index].name)
+                            }
+                        }
+
+                        Text {
+                            Layout.alignment: Qt.AlignRight
+                            text: qsTr("Output device")
+                        }
+
+                        ComboBox {
+                            Layout.alignment: Qt.AlignLeft
+                            Layout.maximumWidth: 300
+                            textRole: "text"
+                            model: deviceEntries(backend.audioDevices.outputs,
+                                                 backend.audioDevices.output)
+                            currentIndex: deviceIndex(model, backend.audioDevices.output)
+
+                            onActivated: {
+                                backend.SetOutputDevice(model[index].name)
+                            }
+                        }
+
                     }
 
                     RowLayout {
@@ -158,16 +328,149 @@ ApplicationWindow {
 
                     RowLayout {
                         Button {
-                            property bool recording: false
-                            text: recording ? qsTr("End recording") :  qsTr("Record input")
+                            text: {
+                                switch (recordingState()) {
+                                case "idle":
+                                    return qsTr("Record input")
+                                case "listening":
+                                    return qsTr("Stop listening")
+                                default:
+                                    return qsTr("End recording")
+                                }
+                            }
 
                             onClicked: {
-                                if (recording) {
+                                if (recordingState() === "idle") {
+                                    backend.StartRecording()
+                                } else {
                                     backend.EndRecording()
+                                }
+                            }
+                        }
+
+                        Button {
+                            visible: recordingState() === "recording" || recordingState() === "paused"
+                            text: recordingState() === "paused" ? qsTr("Resume") : qsTr("Pause")
+
+                            onClicked: {
+                                if (recordingState() === "paused") {
+                                    backend.ResumeRecording()
                                 } else {
-                                    backend.StartRecording()
+                                    backend.PauseRecording()
+                                }
+                            }
+                        }
+
+                        Text {
+                            visible: recordingState() !== "idle"
+                            text: {
+                                var state = backend.recordingState || {}
+                                if (state.state === "listening") {
+                                    return qsTr("Waiting for speech")
                                 }
-                                recording = !recording
+                                var elapsed = formatDuration(state.elapsed || 0)
+                                return state.max > 0 ? elapsed + " / " + formatDuration(state.max) : elapsed
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Normalize")
+
+                            onClicked: {
+                                backend.PreviewNormalization(inputText.text)
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Lexicon")
+
+                            onClicked: {
+                                lexiconEditor.show()
+                                lexiconEditor.raise()
+                            }
+
+                            LexiconEditor {
+                                id: lexiconEditor
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Decoder")
+
+                            onClicked: {
+                                decoderSettings.show()
+                                decoderSettings.raise()
+                            }
+
+                            DecoderSettings {
+                                id: decoderSettings
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Voice activation")
+
+                            onClicked: {
+                                voiceActivation.show()
+                                voiceActivation.raise()
+                            }
+
+                            VoiceActivation {
+                                id: voiceActivation
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Preprocessing")
+
+                            onClicked: {
+                                preprocessing.show()
+                                preprocessing.raise()
+                            }
+
+                            Preprocessing {
+                                id: preprocessing
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Hot words")
+
+                            onClicked: {
+                                hotWordsEditor.show()
+                                hotWordsEditor.raise()
+                            }
+
+                            HotWordsEditor {
+                                id: hotWordsEditor
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Takes")
+
+                            onClicked: {
+                                takes.show()
+                                takes.raise()
+                            }
+
+                            Takes {
+                                id: takes
+                                loopIters: numIters.value
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Explore")
+
+                            onClicked: {
+                                explorationTree.show()
+                                explorationTree.raise()
+                            }
+
+                            ExplorationTree {
+                                id: explorationTree
+                                sourceText: inputText.text
                             }
                         }
                     }
@@ -175,17 +478,33 @@ ApplicationWindow {
                 }
             }
 
+            Text {
+                id: intermediateText
+
+                anchors.right: parent.right
+                anchors.left: parent.left
+
+                text: backend.intermediateText
+                color: "grey"
+                font.italic: true
+                elide: Text.ElideLeft
+            }
+
             Rectangle {
                 anchors.right: parent.right
                 anchors.left: parent.left
 
-                height: root.height - inputLayout.height
+                height: root.height - inputLayout.height - intermediateText.height - 10
 
                 border.color: "lightgrey"
 
                 ListView {
                     id: outputView
 
+                    property int hoveredIndex: -1
+                    // Mouse position in mouseArea, used to find the hovered word
+                    property point hoveredPos
+
                     anchors.fill: parent
                     anchors.margins: 2
 
@@ -200,6 +519,18 @@ ApplicationWindow {
 
                     model: backend.output
                     delegate: Rectangle {
+                        // Tooltip of the link under the mouse, empty if there is none
+                        property string hoveredTooltip: {
+                            if (index != outputView.hoveredIndex) {
+                                return ""
+                            }
+
+                            var pos = outputText.mapFromItem(mouseArea, outputView.hoveredPos.x,
+                                                             outputView.hoveredPos.y)
+                            var link = outputText.linkAt(pos.x, pos.y)
+                            return link != "" ? tooltips[parseInt(link)] || "" : ""
+                        }
+
                         color: selected ? "lightsteelblue" : "white"
 
                         height: outputText.height
@@ -211,6 +542,10 @@ ApplicationWindow {
                             text: display
                             textFormat: TextEdit.RichText
                         }
+
+                        ToolTip.visible: hoveredTooltip != ""
+                        ToolTip.text: hoveredTooltip
+                        ToolTip.delay: 500
                     }
 
                     onContentYChanged: {
@@ -227,7 +562,7 @@ ApplicationWindow {
 
                         preventStealing: true
                         propagateComposedEvents: false
-
+                        hoverEnabled: true
 
                         onPressed: {
                             var contentY = mouseY + outputView.contentY
@@ -236,7 +571,15 @@ ApplicationWindow {
                         }
 
                         onPositionChanged: {
-                            outputView.updateSelectionEnd()
+                            outputView.hoveredIndex = outputView.indexAt(0, mouseY + outputView.contentY)
+                            outputView.hoveredPos = Qt.point(mouseX, mouseY)
+                            if (pressed) {
+                                outputView.updateSelectionEnd()
+                            }
+                        }
+
+                        onExited: {
+                            outputView.hoveredIndex = -1
                         }
                     }
 
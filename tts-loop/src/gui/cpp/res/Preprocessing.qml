@@ -0,0 +1,109 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+
+Window {
+    title: qsTr("Preprocessing")
+    width: 480
+    height: 300
+    minimumWidth: 420
+    minimumHeight: 300
+
+    RowLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+
+        Repeater {
+            model: [
+                { target: "loop", title: qsTr("Loop audio") },
+                { target: "recording", title: qsTr("Recordings") }
+            ]
+
+            GroupBox {
+                property var config: backend.preprocessing[modelData.target] || {}
+
+                Layout.fillWidth: true
+                Layout.fillHeight: true
+                title: modelData.title
+
+                GridLayout {
+                    anchors.fill: parent
+                    columns: 2
+
+                    Text {
+                        text: qsTr("Remove DC")
+                    }
+
+                    CheckBox {
+                        id: removeDc
+                        Layout.leftMargin: 0
+                        checked: config.removeDc || false
+                    }
+
+                    Text {
+                        text: qsTr("Reduce noise")
+                    }
+
+                    CheckBox {
+                        id: denoise
+                        Layout.leftMargin: 0
+                        checked: config.denoise || false
+                    }
+
+                    Text {
+                        text: qsTr("Trim silence")
+                    }
+
+                    CheckBox {
+                        id: trim
+                        Layout.leftMargin: 0
+                        checked: config.trim || false
+                    }
+
+                    Text {
+                        text: qsTr("Padding (s)")
+                    }
+
+                    TextField {
+                        id: pad
+                        Layout.fillWidth: true
+                        text: (config.padSecs || 0).toFixed(2)
+                        validator: DoubleValidator { bottom: 0 }
+                    }
+
+                    Text {
+                        text: qsTr("Normalize")
+                    }
+
+                    ComboBox {
+                        id: normalize
+                        textRole: "text"
+                        model: [
+                            { text: qsTr("Off"), name: "" },
+                            { text: qsTr("Peak"), name: "peak" },
+                            { text: qsTr("Loudness"), name: "loudness" }
+                        ]
+                        currentIndex: Math.max(0, ["", "peak", "loudness"].indexOf(config.normalize || ""))
+                    }
+
+                    Item {
+                        Layout.fillHeight: true
+                    }
+
+                    Button {
+                        Layout.alignment: Qt.AlignRight
+                        text: qsTr("Apply")
+
+                        onClicked: {
+                            backend.ApplyPreprocessing(modelData.target, removeDc.checked,
+                                                       denoise.checked, trim.checked,
+                                                       parseFloat(pad.text) || 0,
+                                                       normalize.model[normalize.currentIndex].name)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+import Qt.labs.platform 1.1
+
+Window {
+    title: qsTr("Takes")
+    width: 640
+    height: 360
+    minimumWidth: 560
+    minimumHeight: 240
+
+    // Iterations run when a take is looped
+    property int loopIters: 10
+
+    function formatDuration(secs) {
+        var total = Math.floor(secs)
+        var s = total % 60
+        return Math.floor(total / 60) + ":" + (s < 10 ? "0" : "") + s
+    }
+
+    ColumnLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+
+        Rectangle {
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+            border.color: "lightgrey"
+
+            ListView {
+                id: entries
+
+                anchors.fill: parent
+                anchors.margins: 2
+                clip: true
+                spacing: 4
+
+                model: backend.takes
+                delegate: ColumnLayout {
+                    width: entries.width
+
+                    RowLayout {
+                        Layout.fillWidth: true
+
+                        TextField {
+                            Layout.fillWidth: true
+                            text: modelData.name
+                            font.bold: true
+
+                            onEditingFinished: {
+                                if (text != modelData.name) {
+                                    backend.RenameTake(modelData.id, text)
+                                }
+                            }
+                        }
+
+                        Text {
+                            text: formatDuration(modelData.duration)
+                        }
+
+                        Button {
+                            text: qsTr("Play")
+
+                            onClicked: {
+                                backend.PlayTake(modelData.id)
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Transcribe")
+
+                            onClicked: {
+                                backend.TranscribeTake(modelData.id)
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Loop")
+
+                            ToolTip.visible: hovered
+                            ToolTip.text: qsTr("Run the loop on the transcript, with this take as iteration 0")
+
+                            onClicked: {
+                                backend.LoopTake(modelData.id, loopIters)
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Export")
+
+                            onClicked: {
+                                exportDialog.takeId = modelData.id
+                                exportDialog.file = ""
+                                exportDialog.open()
+                            }
+                        }
+
+                        Button {
+                            text: qsTr("Remove")
+
+                            onClicked: {
+                                backend.RemoveTake(modelData.id)
+                            }
+                        }
+                    }
+
+                    Text {
+                        Layout.fillWidth: true
+                        text: modelData.text || qsTr("Not transcribed")
+                        color: modelData.text ? "black" : "grey"
+                        elide: Text.ElideRight
+                    }
+                }
+
+                ScrollBar.vertical : ScrollBar {}
+            }
+        }
+    }
+
+    FileDialog {
+        id: exportDialog
+
+        property int takeId: -1
+
+        file: ""
+        folder: StandardPaths.writableLocation(StandardPaths.DocumentsLocation)
+        fileMode: FileDialog.SaveFile
+        nameFilters: [ "Wav files (*.wav)"]
+
+        onAccepted: {
+            backend.ExportTake(takeId, file)
+        }
+    }
+}
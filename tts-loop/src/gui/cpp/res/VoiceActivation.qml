@@ -0,0 +1,87 @@
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import QtQuick.Window 2.15
+import QtQuick 2.15
+
+Window {
+    title: qsTr("Voice activation")
+    width: 420
+    height: 240
+    minimumWidth: 360
+    minimumHeight: 240
+
+    GridLayout {
+        anchors.fill: parent
+        anchors.margins: 15
+        columns: 2
+
+        Text {
+            text: qsTr("Enabled")
+        }
+
+        CheckBox {
+            id: enabled
+            Layout.leftMargin: 0
+            checked: backend.vadConfig.enabled || false
+        }
+
+        Text {
+            text: qsTr("Sensitivity")
+        }
+
+        Slider {
+            id: sensitivity
+            Layout.fillWidth: true
+            from: 0
+            to: 1
+            value: backend.vadConfig.sensitivity !== undefined ? backend.vadConfig.sensitivity : 0.5
+
+            ToolTip.visible: hovered
+            ToolTip.text: qsTr("Higher picks up quieter speech, but also more noise")
+        }
+
+        Text {
+            text: qsTr("Hang-over (s)")
+        }
+
+        TextField {
+            id: hangover
+            Layout.fillWidth: true
+            text: backend.vadConfig.hangoverSecs !== undefined ? backend.vadConfig.hangoverSecs.toFixed(1) : "1.0"
+            validator: DoubleValidator { bottom: 0 }
+
+            ToolTip.visible: hovered
+            ToolTip.text: qsTr("Silence after which speech is considered over")
+        }
+
+        Text {
+            text: qsTr("Loop iterations")
+        }
+
+        SpinBox {
+            id: loopIters
+            from: 0
+            to: 1000
+            editable: true
+            value: backend.vadConfig.loopIters || 0
+
+            ToolTip.visible: hovered
+            ToolTip.text: qsTr("Loop run on each transcript, 0 to only transcribe")
+        }
+
+        Item {
+            Layout.fillHeight: true
+        }
+
+        Button {
+            Layout.alignment: Qt.AlignRight
+            text: qsTr("Apply")
+
+            onClicked: {
+                // An invalid hang-over uses the default
+                backend.ApplyVadConfig(enabled.checked, sensitivity.value,
+                                       parseFloat(hangover.text), loopIters.value)
+            }
+        }
+    }
+}
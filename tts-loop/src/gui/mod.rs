@@ -1,5 +1,16 @@
 use crate::{
+    config::DecoderConfig,
+    explore::{Budget, ExplorationTree},
     gui::rich_text::{Color, Format},
+    hot_words::HotWord,
+    lexicon::LexiconEntry,
+    preprocess::{Normalization, Preprocessing},
+    recognition::Recognition,
+    recording::RecordingState,
+    resample::ResampleQuality,
+    scoring::Scores,
+    takes::Take,
+    vad::VadConfig,
     Request,
 };
 
@@ -9,11 +20,17 @@ use thiserror::Error;
 use std::{
     convert::TryInto,
     ffi::c_void,
+    path::Path,
     sync::{mpsc::Sender, Arc},
+    time::Duration,
 };
 
 mod rich_text;
 
+/// Words recognized with less confidence than this are highlighted
+const LOW_CONFIDENCE: f32 = 0.8;
+const VERY_LOW_CONFIDENCE: f32 = 0.5;
+
 mod imp {
     #![allow(non_snake_case)]
     #![allow(non_upper_case_globals)]
@@ -48,9 +65,86 @@ pub(crate) struct GuiHandle {
 }
 
 impl GuiHandle {
-    pub(crate) fn push_output(&self, text: &str) {
+    /// Pushes the best transcript with uncertain words highlighted. Hovering
+    /// over one of them shows what the other candidates heard in its place
+    pub(crate) fn push_recognition(&self, recognition: &Recognition) {
+        let words = recognition
+            .best()
+            .map(|c| c.words.as_slice())
+            .unwrap_or_default();
+
+        let mut parts = Vec::new();
+        let mut tooltips = Vec::new();
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                parts.push(Format::text(" "));
+            }
+
+            let text = Format::text(&word.text);
+            let color = if word.confidence < VERY_LOW_CONFIDENCE {
+                Color::Red
+            } else if word.confidence < LOW_CONFIDENCE {
+                Color::Orange
+            } else {
+                parts.push(text);
+                continue;
+            };
+
+            // The gui looks the tooltip up by the link target
+            parts.push(Format::link(
+                &tooltips.len().to_string(),
+                Format::color(color, text),
+            ));
+            tooltips.push(
+                recognition
+                    .alternatives(i)
+                    .iter()
+                    .map(|(text, probability)| {
+                        let text = if text.is_empty() { "(nothing)" } else { text };
+                        format!("{:>3.0}%  {}", probability * 100.0, text)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+        let text = Format::concat(parts).into_string();
+        let tooltips = tooltips
+            .iter()
+            .map(|t| to_gui_string(t))
+            .collect::<Vec<_>>();
+
+        unsafe {
+            imp::PushTranscript(
+                **self.handle,
+                to_gui_string(&text),
+                tooltips.as_ptr(),
+                tooltips
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+            );
+        }
+    }
+
+    pub(crate) fn push_scores(&self, scores: &Scores) {
+        let text = Format::color(Color::Grey, Format::text(&scores.to_string())).into_string();
+
         unsafe {
-            imp::PushOutput(**self.handle, to_gui_string(&text));
+            imp::PushRawOutput(**self.handle, to_gui_string(&text));
+        }
+    }
+
+    /// Replaces the partial transcript shown while recognition is in progress
+    pub(crate) fn push_intermediate_text(&self, text: &str) {
+        unsafe {
+            imp::PushIntermediateText(**self.handle, to_gui_string(text));
+        }
+    }
+
+    /// Shows how the text box will be spoken, empty to hide it
+    pub(crate) fn set_normalized_text(&self, text: &str) {
+        unsafe {
+            imp::SetNormalizedText(**self.handle, to_gui_string(text));
         }
     }
 
@@ -60,6 +154,233 @@ impl GuiHandle {
         }
     }
 
+    pub(crate) fn set_lexicon(&self, entries: &[LexiconEntry]) {
+        let phones = entries
+            .iter()
+            .map(|e| e.phones.join(" "))
+            .collect::<Vec<_>>();
+        let gui_words = entries
+            .iter()
+            .map(|e| to_gui_string(&e.word))
+            .collect::<Vec<_>>();
+        let gui_phones = phones.iter().map(|p| to_gui_string(p)).collect::<Vec<_>>();
+
+        unsafe {
+            imp::SetLexicon(
+                **self.handle,
+                gui_words.as_ptr(),
+                gui_phones.as_ptr(),
+                gui_words
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+            );
+        }
+    }
+
+    pub(crate) fn set_hot_words(&self, hot_words: &[HotWord]) {
+        let gui_words = hot_words
+            .iter()
+            .map(|h| to_gui_string(&h.word))
+            .collect::<Vec<_>>();
+        let boosts = hot_words.iter().map(|h| h.boost).collect::<Vec<_>>();
+
+        unsafe {
+            imp::SetHotWords(
+                **self.handle,
+                gui_words.as_ptr(),
+                boosts.as_ptr(),
+                gui_words
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+            );
+        }
+    }
+
+    pub(crate) fn set_takes(&self, takes: &[Take]) {
+        let ids = takes.iter().map(|t| t.id).collect::<Vec<_>>();
+        let names = takes
+            .iter()
+            .map(|t| to_gui_string(&t.name))
+            .collect::<Vec<_>>();
+        let durations = takes
+            .iter()
+            .map(|t| t.duration().as_secs_f32())
+            .collect::<Vec<_>>();
+        let texts = takes
+            .iter()
+            .map(|t| to_gui_string(t.text()))
+            .collect::<Vec<_>>();
+
+        unsafe {
+            imp::SetTakes(
+                **self.handle,
+                ids.as_ptr(),
+                names.as_ptr(),
+                durations.as_ptr(),
+                texts.as_ptr(),
+                ids.len().try_into().expect("usize does not fit in u64"),
+            );
+        }
+    }
+
+    /// Shows the tree depth first, each node with its probability and scores
+    pub(crate) fn set_exploration_tree(&self, tree: &ExplorationTree) {
+        let order = tree.depth_first();
+
+        let depths = order
+            .iter()
+            .map(|id| tree.nodes[*id].depth as u32)
+            .collect::<Vec<_>>();
+        let details = order
+            .iter()
+            .map(|id| {
+                let node = &tree.nodes[*id];
+                let repeat = if node.repeat { ", repeat" } else { "" };
+                format!(
+                    "{:.0}%, {}{}",
+                    node.probability * 100.0,
+                    node.scores,
+                    repeat
+                )
+            })
+            .collect::<Vec<_>>();
+        let gui_texts = order
+            .iter()
+            .map(|id| to_gui_string(&tree.nodes[*id].text))
+            .collect::<Vec<_>>();
+        let gui_details = details.iter().map(|d| to_gui_string(d)).collect::<Vec<_>>();
+
+        unsafe {
+            imp::SetExplorationTree(
+                **self.handle,
+                depths.as_ptr(),
+                gui_texts.as_ptr(),
+                gui_details.as_ptr(),
+                gui_texts
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+            );
+        }
+    }
+
+    pub(crate) fn set_model_path(&self, path: &Path) {
+        let path = path.to_string_lossy();
+
+        unsafe {
+            imp::SetModelPath(**self.handle, to_gui_string(&path));
+        }
+    }
+
+    pub(crate) fn set_resample_quality(&self, quality: ResampleQuality) {
+        let quality = quality.to_string();
+
+        unsafe {
+            imp::SetResampleQuality(**self.handle, to_gui_string(&quality));
+        }
+    }
+
+    /// Lists the devices that can be selected. Empty selections are the system
+    /// default
+    pub(crate) fn set_audio_devices(
+        &self,
+        inputs: &[String],
+        outputs: &[String],
+        input: Option<&str>,
+        output: Option<&str>,
+    ) {
+        let gui_inputs = inputs.iter().map(|s| to_gui_string(s)).collect::<Vec<_>>();
+        let gui_outputs = outputs.iter().map(|s| to_gui_string(s)).collect::<Vec<_>>();
+
+        unsafe {
+            imp::SetAudioDevices(
+                **self.handle,
+                gui_inputs.as_ptr(),
+                gui_inputs
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+                gui_outputs.as_ptr(),
+                gui_outputs
+                    .len()
+                    .try_into()
+                    .expect("usize does not fit in u64"),
+                to_gui_string(input.unwrap_or_default()),
+                to_gui_string(output.unwrap_or_default()),
+            );
+        }
+    }
+
+    /// Updates the record controls. A max of None means recordings have no
+    /// limit
+    pub(crate) fn set_recording_state(
+        &self,
+        state: RecordingState,
+        elapsed: Duration,
+        max: Option<Duration>,
+    ) {
+        unsafe {
+            imp::SetRecordingState(
+                **self.handle,
+                to_gui_string(state.name()),
+                elapsed.as_secs_f32(),
+                max.map(|max| max.as_secs_f32()).unwrap_or(0.0),
+            );
+        }
+    }
+
+    /// config is expected to have every value filled in
+    pub(crate) fn set_vad_config(&self, config: &VadConfig) {
+        unsafe {
+            imp::SetVadConfig(
+                **self.handle,
+                config.enabled(),
+                config.sensitivity(),
+                config.hangover().as_secs_f32(),
+                config.loop_iters(),
+            );
+        }
+    }
+
+    pub(crate) fn set_preprocessing(&self, loop_audio: &Preprocessing, recordings: &Preprocessing) {
+        for (target, config) in [("loop", loop_audio), ("recording", recordings)] {
+            let normalize = config.normalize.map(|n| n.to_string()).unwrap_or_default();
+            unsafe {
+                imp::SetPreprocessing(
+                    **self.handle,
+                    to_gui_string(target),
+                    config.remove_dc,
+                    config.denoise,
+                    config.trim,
+                    config.pad_secs,
+                    to_gui_string(&normalize),
+                );
+            }
+        }
+    }
+
+    pub(crate) fn set_decoder_config(&self, config: &DecoderConfig) {
+        let scorer = config
+            .scorer
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let alpha = config.alpha.map(|a| a.to_string()).unwrap_or_default();
+        let beta = config.beta.map(|b| b.to_string()).unwrap_or_default();
+
+        unsafe {
+            imp::SetDecoderConfig(
+                **self.handle,
+                to_gui_string(&scorer),
+                to_gui_string(&alpha),
+                to_gui_string(&beta),
+                config.beam_width.unwrap_or(0).into(),
+            );
+        }
+    }
+
     pub(crate) fn log(&self, text: &str, level: Level) {
         let encoded = Format::bold(Format::text(text));
 
@@ -120,10 +441,36 @@ pub(crate) fn run(tx: Sender<Request>, voices: &[&str]) -> GuiHandle {
                 start_tts_loop: Some(start_tts_loop),
                 set_voice: Some(set_voice),
                 enable_audio: Some(enable_audio),
+                enable_phoneme_scoring: Some(enable_phoneme_scoring),
+                enable_normalized_scoring: Some(enable_normalized_scoring),
+                enable_streaming_stt: Some(enable_streaming_stt),
+                set_resample_quality: Some(set_resample_quality),
+                set_vad_config: Some(set_vad_config),
+                set_preprocessing: Some(set_preprocessing),
+                set_input_device: Some(set_input_device),
+                set_output_device: Some(set_output_device),
+                preview_normalization: Some(preview_normalization),
                 cancel: Some(cancel),
                 save: Some(save),
                 start_recording: Some(start_recording),
                 end_recording: Some(end_recording),
+                pause_recording: Some(pause_recording),
+                resume_recording: Some(resume_recording),
+                play_take: Some(play_take),
+                rename_take: Some(rename_take),
+                export_take: Some(export_take),
+                transcribe_take: Some(transcribe_take),
+                remove_take: Some(remove_take),
+                loop_take: Some(loop_take),
+                set_lexicon_entry: Some(set_lexicon_entry),
+                remove_lexicon_entry: Some(remove_lexicon_entry),
+                preview_word: Some(preview_word),
+                set_decoder_config: Some(set_decoder_config),
+                set_hot_word: Some(set_hot_word),
+                remove_hot_word: Some(remove_hot_word),
+                set_model: Some(set_model),
+                explore: Some(explore),
+                save_exploration: Some(save_exploration),
             },
             gui_voices.as_ptr(),
             gui_voices
@@ -198,6 +545,151 @@ unsafe extern "C" fn enable_audio(enable: bool, data: *const c_void) {
     let _ = data.tx.send(Request::EnableAudio { enable });
 }
 
+unsafe extern "C" fn enable_phoneme_scoring(enable: bool, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::EnablePhonemeScoring { enable });
+}
+
+unsafe extern "C" fn enable_streaming_stt(enable: bool, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::EnableStreamingStt { enable });
+}
+
+unsafe extern "C" fn enable_normalized_scoring(enable: bool, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::EnableNormalizedScoring { enable });
+}
+
+unsafe extern "C" fn set_resample_quality(quality: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let quality = match parse_gui_string(&quality).map(str::parse::<ResampleQuality>) {
+        Ok(Ok(quality)) => quality,
+        Ok(Err(e)) => {
+            error!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::SetResampleQuality { quality });
+}
+
+unsafe extern "C" fn set_vad_config(
+    enabled: bool,
+    sensitivity: f32,
+    hangover_secs: f32,
+    loop_iters: u32,
+    data: *const c_void,
+) {
+    let data = data_to_inner(data);
+    let config = VadConfig {
+        enabled: Some(enabled),
+        sensitivity: Some(sensitivity),
+        hangover_secs: Some(hangover_secs),
+        loop_iters: Some(loop_iters),
+    };
+    let _ = data.tx.send(Request::SetVadConfig { config });
+}
+
+unsafe extern "C" fn set_preprocessing(
+    target: imp::String,
+    remove_dc: bool,
+    denoise: bool,
+    trim: bool,
+    pad_secs: f32,
+    normalize: imp::String,
+    data: *const c_void,
+) {
+    let data = data_to_inner(data);
+
+    let (target, normalize) = match (parse_gui_string(&target), parse_gui_string(&normalize)) {
+        (Ok(target), Ok(normalize)) => (target, normalize),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let normalize = match normalize {
+        "" => None,
+        normalize => match normalize.parse::<Normalization>() {
+            Ok(normalize) => Some(normalize),
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        },
+    };
+
+    let config = Preprocessing {
+        remove_dc,
+        denoise,
+        trim,
+        pad_secs: if pad_secs.is_finite() {
+            pad_secs.max(0.0)
+        } else {
+            0.0
+        },
+        normalize,
+    };
+
+    let req = match target {
+        "loop" => Request::SetLoopPreprocessing { config },
+        "recording" => Request::SetRecordingPreprocessing { config },
+        _ => {
+            error!("Invalid preprocessing target: {}", target);
+            return;
+        }
+    };
+    let _ = data.tx.send(req);
+}
+
+/// Parses a device name from the GUI, where an empty name is the system default
+fn device_name(name: &imp::String) -> Option<Option<String>> {
+    match parse_gui_string(name) {
+        Ok("") => Some(None),
+        Ok(name) => Some(Some(name.to_string())),
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            None
+        }
+    }
+}
+
+unsafe extern "C" fn set_input_device(name: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+    if let Some(name) = device_name(&name) {
+        let _ = data.tx.send(Request::SetInputDevice { name });
+    }
+}
+
+unsafe extern "C" fn set_output_device(name: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+    if let Some(name) = device_name(&name) {
+        let _ = data.tx.send(Request::SetOutputDevice { name });
+    }
+}
+
+unsafe extern "C" fn preview_normalization(text: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let text = match parse_gui_string(&text) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::PreviewNormalization {
+        text: text.to_string(),
+    });
+}
+
 unsafe extern "C" fn save(path: imp::String, data: *const c_void) {
     let data = data_to_inner(data);
 
@@ -221,3 +713,271 @@ unsafe extern "C" fn end_recording(data: *const c_void) {
     let data = data_to_inner(data);
     let _ = data.tx.send(Request::EndRecording);
 }
+
+unsafe extern "C" fn pause_recording(data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::PauseRecording);
+}
+
+unsafe extern "C" fn resume_recording(data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::ResumeRecording);
+}
+
+unsafe extern "C" fn play_take(take: u32, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::PlayTake { take });
+}
+
+unsafe extern "C" fn rename_take(take: u32, name: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let name = match parse_gui_string(&name) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::RenameTake {
+        take,
+        name: name.to_string(),
+    });
+}
+
+unsafe extern "C" fn export_take(take: u32, path: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let path = match parse_gui_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::ExportTake {
+        take,
+        path: path.into(),
+    });
+}
+
+unsafe extern "C" fn transcribe_take(take: u32, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::TranscribeTake { take });
+}
+
+unsafe extern "C" fn remove_take(take: u32, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::RemoveTake { take });
+}
+
+unsafe extern "C" fn loop_take(take: u32, num_iters: i32, data: *const c_void) {
+    let data = data_to_inner(data);
+    let _ = data.tx.send(Request::LoopTake { take, num_iters });
+}
+
+unsafe extern "C" fn set_lexicon_entry(
+    word: imp::String,
+    phones: imp::String,
+    data: *const c_void,
+) {
+    let data = data_to_inner(data);
+
+    let (word, phones) = match (parse_gui_string(&word), parse_gui_string(&phones)) {
+        (Ok(word), Ok(phones)) => (word, phones),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::SetLexiconEntry {
+        word: word.to_string(),
+        phones: phones.to_string(),
+    });
+}
+
+unsafe extern "C" fn remove_lexicon_entry(word: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let word = match parse_gui_string(&word) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::RemoveLexiconEntry {
+        word: word.to_string(),
+    });
+}
+
+unsafe extern "C" fn set_hot_word(word: imp::String, boost: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let (word, boost) = match (parse_gui_string(&word), parse_gui_string(&boost)) {
+        (Ok(word), Ok(boost)) => (word, boost),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::SetHotWord {
+        word: word.to_string(),
+        boost: boost.to_string(),
+    });
+}
+
+unsafe extern "C" fn remove_hot_word(word: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let word = match parse_gui_string(&word) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::RemoveHotWord {
+        word: word.to_string(),
+    });
+}
+
+unsafe extern "C" fn set_model(path: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let path = match parse_gui_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::SetModel { path: path.into() });
+}
+
+unsafe extern "C" fn explore(
+    text: imp::String,
+    branching: u32,
+    max_depth: u32,
+    max_nodes: u32,
+    data: *const c_void,
+) {
+    let data = data_to_inner(data);
+
+    let text = match parse_gui_string(&text) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::Explore {
+        text: text.to_string(),
+        budget: Budget {
+            branching: branching.max(1) as usize,
+            max_depth: max_depth.max(1) as usize,
+            max_nodes: max_nodes.max(1) as usize,
+        },
+    });
+}
+
+unsafe extern "C" fn save_exploration(path: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let path = match parse_gui_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::SaveExploration { path: path.into() });
+}
+
+unsafe extern "C" fn preview_word(word: imp::String, data: *const c_void) {
+    let data = data_to_inner(data);
+
+    let word = match parse_gui_string(&word) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let _ = data.tx.send(Request::PreviewWord {
+        word: word.to_string(),
+    });
+}
+
+unsafe extern "C" fn set_decoder_config(
+    scorer: imp::String,
+    alpha: imp::String,
+    beta: imp::String,
+    beam_width: u32,
+    data: *const c_void,
+) {
+    let data = data_to_inner(data);
+
+    let (scorer, alpha, beta) = match (
+        parse_gui_string(&scorer),
+        parse_gui_string(&alpha),
+        parse_gui_string(&beta),
+    ) {
+        (Ok(scorer), Ok(alpha), Ok(beta)) => (scorer, alpha, beta),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Invalid gui string: {}", e);
+            return;
+        }
+    };
+
+    let parse_weight = |s: &str| -> Result<Option<f32>, std::num::ParseFloatError> {
+        let s = s.trim();
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            s.parse().map(Some)
+        }
+    };
+
+    let (alpha, beta) = match (parse_weight(alpha), parse_weight(beta)) {
+        (Ok(alpha), Ok(beta)) => (alpha, beta),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Invalid decoder weight: {}", e);
+            return;
+        }
+    };
+
+    let beam_width = match beam_width {
+        0 => None,
+        w => match w.try_into() {
+            Ok(w) => Some(w),
+            Err(_) => {
+                error!("Beam width {} is too large", w);
+                return;
+            }
+        },
+    };
+
+    let config = DecoderConfig {
+        scorer: if scorer.is_empty() {
+            None
+        } else {
+            Some(scorer.into())
+        },
+        alpha,
+        beta,
+        beam_width,
+    };
+
+    let _ = data.tx.send(Request::SetDecoderConfig { config });
+}
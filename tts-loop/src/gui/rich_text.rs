@@ -1,6 +1,7 @@
 pub(crate) enum Color {
     Blue,
     Green,
+    Grey,
     Orange,
     Red,
 }
@@ -9,6 +10,7 @@ impl Color {
     fn to_str(&self) -> &'static str {
         match *self {
             Color::Green => "green",
+            Color::Grey => "grey",
             Color::Blue => "blue",
             Color::Red => "red",
             Color::Orange => "orange",
@@ -19,6 +21,8 @@ impl Color {
 enum FormatInner {
     Color(Color, Box<Format>),
     Bold(Box<Format>),
+    Link(String, Box<Format>),
+    Concat(Vec<Box<Format>>),
     Text(String),
 }
 pub(crate) struct Format {
@@ -43,6 +47,21 @@ impl Format {
         })
     }
 
+    /// Link to href without the usual link styling, so that the contents keep
+    /// their own color
+    pub(crate) fn link(href: &str, f: Box<Format>) -> Box<Format> {
+        let href = v_htmlescape::escape(href).to_string();
+        Box::new(Format {
+            inner: FormatInner::Link(href, f),
+        })
+    }
+
+    pub(crate) fn concat(parts: Vec<Box<Format>>) -> Box<Format> {
+        Box::new(Format {
+            inner: FormatInner::Concat(parts),
+        })
+    }
+
     pub(crate) fn text(s: &str) -> Box<Format> {
         let s = v_htmlescape::escape(s).to_string();
         Box::new(Format {
@@ -64,6 +83,18 @@ impl Format {
                 s = format.into_string_impl(s);
                 s.push_str("</b>")
             }
+            FormatInner::Link(href, format) => {
+                s.push_str("<a href=\"");
+                s.push_str(&href);
+                s.push_str("\" style=\"text-decoration:none\">");
+                s = format.into_string_impl(s);
+                s.push_str("</a>")
+            }
+            FormatInner::Concat(parts) => {
+                for part in parts {
+                    s = part.into_string_impl(s);
+                }
+            }
             FormatInner::Text(text) => {
                 s.push_str(&text);
             }
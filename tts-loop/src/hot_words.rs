@@ -0,0 +1,207 @@
+use crate::config;
+
+use deepspeech::{errors::DeepspeechError, Model as DsModel};
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Failed to read hot words {path}: {err}")]
+    Read { path: PathBuf, err: io::Error },
+    #[error("Failed to write hot words {path}: {err}")]
+    Write { path: PathBuf, err: io::Error },
+    #[error("Invalid hot word \"{0}\"")]
+    InvalidWord(String),
+    #[error("Invalid boost \"{boost}\" for hot word \"{word}\"")]
+    InvalidBoost { word: String, boost: String },
+    #[error("No hot word \"{0}\"")]
+    NotFound(String),
+    #[error(transparent)]
+    DeepspeechError(#[from] DeepspeechError),
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HotWord {
+    pub word: String,
+    pub boost: f32,
+}
+
+impl std::str::FromStr for HotWord {
+    type Err = Error;
+
+    /// Parses `word:boost`, as given on the command line
+    fn from_str(s: &str) -> Result<HotWord, Error> {
+        let (word, boost) = s
+            .rsplit_once(':')
+            .ok_or_else(|| Error::InvalidWord(s.to_string()))?;
+        HotWord::new(word, boost)
+    }
+}
+
+impl HotWord {
+    fn new(word: &str, boost: &str) -> Result<HotWord, Error> {
+        let word = normalize_word(word)?;
+
+        let boost = match boost.trim().parse::<f32>() {
+            Ok(boost) if boost.is_finite() => boost,
+            _ => {
+                return Err(Error::InvalidBoost {
+                    word,
+                    boost: boost.to_string(),
+                })
+            }
+        };
+
+        Ok(HotWord { word, boost })
+    }
+}
+
+fn insert(entries: &mut Vec<HotWord>, hot_word: HotWord) {
+    match entries.iter_mut().find(|e| e.word == hot_word.word) {
+        Some(entry) => entry.boost = hot_word.boost,
+        None => entries.push(hot_word),
+    }
+}
+
+/// Hot words are stored lowercase, the way DeepSpeech transcribes them
+fn normalize_word(word: &str) -> Result<String, Error> {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(Error::InvalidWord(word));
+    }
+
+    Ok(word)
+}
+
+/// Words the recognizer is biased towards (positive boost) or away from
+/// (negative boost). Stored one entry per line as `word boost`, lines starting
+/// with `#` are ignored
+pub(crate) struct HotWords {
+    path: PathBuf,
+    /// Entries from the file
+    entries: Vec<HotWord>,
+    /// Entries given on the command line, used for this run only. They replace
+    /// the boost of stored entries for the same word
+    overrides: Vec<HotWord>,
+}
+
+pub(crate) fn default_path() -> PathBuf {
+    config::config_dir().join("hotwords.txt")
+}
+
+impl HotWords {
+    /// Loads the hot words at path. A missing file is treated as an empty list
+    pub(crate) fn load(path: PathBuf) -> Result<HotWords, Error> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::Read { path, err }),
+        };
+
+        let mut hot_words = HotWords {
+            path,
+            entries: Vec::new(),
+            overrides: Vec::new(),
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let word = tokens.next().unwrap_or_default();
+            let boost = tokens.collect::<Vec<_>>().join(" ");
+            hot_words.set_entry(word, &boost)?;
+        }
+
+        Ok(hot_words)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        let write = || -> io::Result<()> {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut f = fs::File::create(&self.path)?;
+            for entry in &self.entries {
+                writeln!(f, "{} {}", entry.word, entry.boost)?;
+            }
+            Ok(())
+        };
+
+        write().map_err(|err| Error::Write {
+            path: self.path.clone(),
+            err,
+        })
+    }
+
+    /// Stored entries with the command line overrides applied
+    pub(crate) fn entries(&self) -> Vec<HotWord> {
+        let mut ret = self.entries.clone();
+        for hot_word in &self.overrides {
+            insert(&mut ret, hot_word.clone());
+        }
+        ret
+    }
+
+    /// Entries the model uses, none while no external scorer is enabled
+    pub(crate) fn active(&self, scorer_enabled: bool) -> Vec<HotWord> {
+        if scorer_enabled {
+            self.entries()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Adds or replaces the boost for word. The entry is stored and replaces
+    /// any command line override of the word
+    pub(crate) fn set_entry(&mut self, word: &str, boost: &str) -> Result<(), Error> {
+        let hot_word = HotWord::new(word, boost)?;
+        self.overrides.retain(|e| e.word != hot_word.word);
+        insert(&mut self.entries, hot_word);
+        Ok(())
+    }
+
+    /// Adds or replaces the boost for word for this run, without storing it
+    pub(crate) fn set_override(&mut self, hot_word: HotWord) {
+        insert(&mut self.overrides, hot_word);
+    }
+
+    /// Removes word from both the stored entries and the overrides
+    pub(crate) fn remove_entry(&mut self, word: &str) -> Result<(), Error> {
+        let word = normalize_word(word)?;
+
+        let num_entries = self.entries.len() + self.overrides.len();
+        self.entries.retain(|e| e.word != word);
+        self.overrides.retain(|e| e.word != word);
+        if self.entries.len() + self.overrides.len() == num_entries {
+            return Err(Error::NotFound(word));
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the model's hot words with the entries. DeepSpeech rejects hot
+    /// words while no external scorer is enabled, so without one the entries
+    /// are only kept here until a scorer is enabled and this is called again
+    pub(crate) fn apply(&self, model: &mut DsModel, scorer_enabled: bool) -> Result<(), Error> {
+        if !scorer_enabled {
+            return Ok(());
+        }
+
+        model.clear_hot_words()?;
+        for entry in &self.entries() {
+            model.add_hot_word(&entry.word, entry.boost)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::config;
+
+use thiserror::Error as ThisError;
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Failed to read lexicon {path}: {err}")]
+    Read { path: PathBuf, err: io::Error },
+    #[error("Failed to write lexicon {path}: {err}")]
+    Write { path: PathBuf, err: io::Error },
+    #[error("No phones given for \"{0}\"")]
+    MissingPhones(String),
+    #[error("Invalid lexicon word \"{0}\"")]
+    InvalidWord(String),
+    #[error("Phone \"{phone}\" for \"{word}\" is not in the lexicon phoneset")]
+    InvalidPhone { word: String, phone: String },
+    #[error("Invalid entry in lexicon {path} on line {line}: {err}")]
+    InvalidLine {
+        path: PathBuf,
+        line: usize,
+        err: Box<Error>,
+    },
+    #[error(transparent)]
+    NulError(#[from] std::ffi::NulError),
+}
+
+pub(crate) struct LexiconEntry {
+    pub(crate) word: String,
+    pub(crate) phones: Vec<String>,
+}
+
+/// User pronunciations layered on top of flite's lexicon. Stored one entry per
+/// line as `word phone phone ...`, lines starting with `#` are ignored
+pub(crate) struct Lexicon {
+    path: PathBuf,
+    entries: Vec<LexiconEntry>,
+}
+
+pub(crate) fn default_path() -> PathBuf {
+    config::config_dir().join("lexicon.txt")
+}
+
+impl Lexicon {
+    /// Loads the lexicon at path. A missing file is treated as an empty lexicon
+    pub(crate) fn load(path: PathBuf) -> Result<Lexicon, Error> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::Read { path, err }),
+        };
+
+        let mut lexicon = Lexicon {
+            path,
+            entries: Vec::new(),
+        };
+
+        let valid_phones = flite::lex_phones();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (word, phones) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match parse_entry(word, phones, &valid_phones) {
+                Ok(entry) => lexicon.insert(entry),
+                Err(err) => {
+                    return Err(Error::InvalidLine {
+                        path: lexicon.path,
+                        line: i + 1,
+                        err: Box::new(err),
+                    })
+                }
+            }
+        }
+
+        Ok(lexicon)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        let write = || -> io::Result<()> {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut f = fs::File::create(&self.path)?;
+            for entry in &self.entries {
+                writeln!(f, "{} {}", entry.word, entry.phones.join(" "))?;
+            }
+            Ok(())
+        };
+
+        write().map_err(|err| Error::Write {
+            path: self.path.clone(),
+            err,
+        })
+    }
+
+    pub(crate) fn entries(&self) -> &[LexiconEntry] {
+        &self.entries
+    }
+
+    /// Adds or replaces the pronunciation for word. Phones are separated by
+    /// whitespace and must be part of flite's phoneset
+    pub(crate) fn set_entry(&mut self, word: &str, phones: &str) -> Result<(), Error> {
+        let entry = parse_entry(word, phones, &flite::lex_phones())?;
+        self.insert(entry);
+        Ok(())
+    }
+
+    fn insert(&mut self, entry: LexiconEntry) {
+        match self.entries.iter_mut().find(|e| e.word == entry.word) {
+            Some(existing) => existing.phones = entry.phones,
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub(crate) fn remove_entry(&mut self, word: &str) -> Result<(), Error> {
+        let word = normalize_word(word)?;
+        self.entries.retain(|e| e.word != word);
+        Ok(())
+    }
+
+    /// Pushes the entries into flite so they are used for all future synthesis
+    pub(crate) fn apply(&self) -> Result<(), Error> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|e| flite::LexEntry {
+                word: e.word.clone(),
+                phones: e.phones.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        flite::set_lex_addenda(&entries)?;
+        Ok(())
+    }
+}
+
+/// Words are stored lowercase, the way flite looks them up
+fn normalize_word(word: &str) -> Result<String, Error> {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() || word.contains(char::is_whitespace) || word.contains('"') {
+        return Err(Error::InvalidWord(word));
+    }
+
+    Ok(word)
+}
+
+fn parse_entry(word: &str, phones: &str, valid_phones: &[String]) -> Result<LexiconEntry, Error> {
+    let word = normalize_word(word)?;
+
+    let phones = phones
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if phones.is_empty() {
+        return Err(Error::MissingPhones(word));
+    }
+
+    if let Some(phone) = phones.iter().find(|p| !valid_phones.contains(p)) {
+        return Err(Error::InvalidPhone {
+            word,
+            phone: phone.clone(),
+        });
+    }
+
+    Ok(LexiconEntry { word, phones })
+}
@@ -1,41 +1,116 @@
-use crate::{audio::AudioManager, gui::GuiHandle};
+use crate::{
+    audio::{AudioManager, StreamingPlayback},
+    devices::{DeviceSelection, Direction},
+    explore::{Budget, Exploration, ExplorationTree},
+    gui::GuiHandle,
+    hot_words::HotWords,
+    lexicon::Lexicon,
+    model::ModelInfo,
+    preprocess::Preprocessing,
+    recognition::Recognition,
+    recording::{Recording, RecordingState},
+    resample::ResampleQuality,
+    results::{Iteration, RunResults},
+    scoring::{Reference, ScoringOptions},
+    stt_stream::SttStream,
+    takes::Takes,
+    vad::VadConfig,
+};
 
 use deepspeech::{errors::DeepspeechError, Model as DsModel};
 use hound::{WavSpec, WavWriter};
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use thiserror::Error as ThisError;
 
-use std::convert::TryFrom;
 use std::{
     convert::TryInto,
     path::{Path, PathBuf},
     sync::{
-        mpsc::{self, Receiver},
+        mpsc::{self, Receiver, RecvTimeoutError},
         Arc,
     },
+    time::Duration,
 };
 
 mod audio;
+mod config;
+mod devices;
+mod explore;
 mod gui;
+mod hot_words;
+mod lexicon;
 mod logger;
+mod model;
+mod preprocess;
+mod recognition;
+mod recording;
+mod resample;
+mod results;
+mod scoring;
+mod stt_stream;
+mod takes;
+mod vad;
 
+pub use audio::VirtualAudio;
+pub use config::{DecoderConfig, Options, MODEL_ENV_VAR};
+pub use devices::{device_names, Direction};
+pub use hot_words::HotWord;
 pub use logger::init_logger;
-
-const SAMPLE_RATE: u32 = 16000;
+pub use preprocess::{Normalization, Preprocessing};
+pub use resample::ResampleQuality;
+pub use vad::VadConfig;
 
 pub(crate) enum Request {
     TtsLoop { text: String, num_iters: i32 },
     SetVoice { voice: String },
     EnableAudio { enable: bool },
+    EnablePhonemeScoring { enable: bool },
+    EnableNormalizedScoring { enable: bool },
+    EnableStreamingStt { enable: bool },
+    SetDecoderConfig { config: DecoderConfig },
+    SetResampleQuality { quality: ResampleQuality },
+    SetVadConfig { config: VadConfig },
+    SetLoopPreprocessing { config: Preprocessing },
+    SetRecordingPreprocessing { config: Preprocessing },
+    SetInputDevice { name: Option<String> },
+    SetOutputDevice { name: Option<String> },
+    PreviewNormalization { text: String },
     Cancel,
     Shutdown,
     Save { path: PathBuf },
     StartRecording,
     EndRecording,
+    PauseRecording,
+    ResumeRecording,
+    PlayTake { take: u32 },
+    RenameTake { take: u32, name: String },
+    ExportTake { take: u32, path: PathBuf },
+    TranscribeTake { take: u32 },
+    RemoveTake { take: u32 },
+    LoopTake { take: u32, num_iters: i32 },
+    SetLexiconEntry { word: String, phones: String },
+    RemoveLexiconEntry { word: String },
+    SetHotWord { word: String, boost: String },
+    RemoveHotWord { word: String },
+    SetModel { path: PathBuf },
+    Explore { text: String, budget: Budget },
+    SaveExploration { path: PathBuf },
+    PreviewWord { word: String },
 }
 
 struct Settings {
     enable_audio: bool,
+    streaming_stt: bool,
+    scoring: ScoringOptions,
+    decoder: DecoderConfig,
+    resample_quality: ResampleQuality,
+    /// Recordings stop by themselves after this long
+    max_recording: Option<Duration>,
+    /// Voice activated recording, with every value filled in
+    vad: VadConfig,
+    /// Applied before recognition, to the loop's audio and to recordings
+    loop_preprocessing: Preprocessing,
+    recording_preprocessing: Preprocessing,
     voice: String,
 }
 
@@ -52,6 +127,12 @@ struct LoopState {
     wav: Vec<i16>,
     last_frame_len: usize,
     remaining_iters: usize,
+    reference: Reference,
+    results: RunResults,
+    /// Playback started while the last frame was being synthesized
+    playback: Option<StreamingPlayback>,
+    /// Recognition fed while the last frame was being synthesized
+    stt_stream: Option<SttStream>,
 }
 
 impl LoopState {
@@ -62,6 +143,10 @@ impl LoopState {
             wav: Vec::new(),
             last_frame_len: 0,
             remaining_iters: 0,
+            reference: Reference::default(),
+            results: RunResults::default(),
+            playback: None,
+            stt_stream: None,
         }
     }
 
@@ -80,49 +165,14 @@ impl LoopState {
 
     fn set_finished(&mut self) {
         self.phase = LoopStatePhase::Finished;
+        self.playback = None;
+        self.stt_stream = None;
     }
 }
 
-enum Recording {
-    Ongoing {
-        _stream: cpal::Stream,
-        rx: mpsc::Receiver<Vec<i16>>,
-    },
-    Finished {
-        buf: Vec<i16>,
-    },
-}
-
-impl Recording {
-    fn start_recording(&mut self, audio_manager: &AudioManager) -> Result<(), Error> {
-        match self {
-            Recording::Ongoing { .. } => (),
-            Recording::Finished { .. } => {
-                let (tx, rx) = mpsc::channel();
-
-                let _stream = audio_manager.input_stream(SAMPLE_RATE, move |buf| {
-                    let _ = tx.send(buf.to_owned());
-                })?;
-
-                *self = Recording::Ongoing { _stream, rx };
-            }
-        }
-
-        Ok(())
-    }
-
-    fn stop_recording(&mut self) {
-        let buf = match self {
-            Recording::Finished { .. } => return,
-            Recording::Ongoing { rx, .. } => rx
-                .try_iter()
-                .flat_map(|v| v.into_iter())
-                .collect::<Vec<i16>>(),
-        };
-
-        *self = Recording::Finished { buf }
-    }
-}
+/// How often recorded audio is fed to recognition while the app is otherwise
+/// idle
+const RECORDING_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(ThisError, Debug)]
 pub enum Error {
@@ -130,14 +180,40 @@ pub enum Error {
     DeepspeechError(#[from] DeepspeechError),
     #[error(transparent)]
     AudioManager(#[from] audio::Error),
+    #[error(transparent)]
+    Config(#[from] config::Error),
+    #[error(transparent)]
+    Model(#[from] model::Error),
+    #[error(transparent)]
+    Devices(#[from] devices::Error),
+    #[error(transparent)]
+    Lexicon(#[from] lexicon::Error),
+    #[error(transparent)]
+    Recording(#[from] recording::Error),
+    #[error(transparent)]
+    Takes(#[from] takes::Error),
+    #[error(transparent)]
+    HotWords(#[from] hot_words::Error),
+    #[error(transparent)]
+    Explore(#[from] explore::Error),
+    #[error(transparent)]
+    Flite(#[from] flite::Error),
     #[error("Action canceled by user")]
     Canceled,
     #[error("Failed to write wav to file: {0}")]
     WavWriteError(hound::Error),
+    #[error("Failed to write results to file: {0}")]
+    ResultsWriteError(std::io::Error),
     #[error("Data not available")]
     NoData,
     #[error("Recording in progress")]
     CurrentlyRecording,
+    #[error("Speech recognition is disabled, libdeepspeech could not be loaded")]
+    SttUnavailable,
+    #[error("No flite voice was built in")]
+    NoVoices,
+    #[error("Invalid number of iterations {0}")]
+    InvalidIterations(i32),
     #[error("Gui request handle no longer active")]
     GuiRecvError,
     #[error("Invalid C string")]
@@ -155,51 +231,132 @@ enum AppState {
 }
 
 pub struct TtsLooper {
-    stt_model: DsModel,
+    // Declared before stt_model so that any recognition stream is freed before
+    // the model it was created from
+    work: LoopState,
+    recording: Recording,
+    takes: Takes,
+    /// None if libdeepspeech could not be loaded
+    stt_model: Option<DsModel>,
+    /// Why stt_model is None, reported once the logger is up
+    stt_disabled_reason: Option<String>,
+    model_info: ModelInfo,
     audio_manager: AudioManager,
+    device_selection: DeviceSelection,
     gui: Arc<GuiHandle>,
     gui_rx: Receiver<Request>,
-    work: LoopState,
-    recording: Recording,
+    lexicon: Lexicon,
+    hot_words: HotWords,
+    /// Latest n-best exploration, kept after it finishes so it can be saved
+    exploration: Option<Exploration>,
     settings: Settings,
 }
 
 impl TtsLooper {
-    pub fn new() -> Result<TtsLooper, Error> {
-        let path =
-            &Path::new(env!("CARGO_MANIFEST_DIR")).join("res/deepspeech-0.9.3-models.tflite");
-        let stt_model = DsModel::load_from_files(&path)?;
+    pub fn new(options: Options) -> Result<TtsLooper, Error> {
+        let options = options.resolve()?;
+
+        let mut hot_words = HotWords::load(hot_words::default_path())?;
+        for hot_word in options.hot_words.iter().cloned() {
+            hot_words.set_override(hot_word);
+        }
 
-        let sample_rate = stt_model.get_sample_rate();
-        assert!(u32::try_from(sample_rate).unwrap() == SAMPLE_RATE);
+        let stt_disabled_reason = model::load_library(&options.library_paths)
+            .err()
+            .map(|e| e.to_string());
+        let (stt_model, model_info) = match stt_disabled_reason {
+            None => {
+                let (stt_model, model_info) =
+                    load_stt_model(&options.model_path(), &options.decoder, &hot_words)?;
+                (Some(stt_model), model_info)
+            }
+            Some(_) => {
+                let model_info = ModelInfo {
+                    path: options.model_path(),
+                    ..ModelInfo::default()
+                };
+                (None, model_info)
+            }
+        };
 
-        let audio_manager = AudioManager::new();
+        let resample_quality = options.resample_quality.unwrap_or_default();
+        let device_selection = DeviceSelection::load(devices::default_path())?;
+        // Devices given on the command line only apply to this run
+        let input_device = options
+            .input_device
+            .or_else(|| device_selection.input.clone());
+        let output_device = options
+            .output_device
+            .or_else(|| device_selection.output.clone());
+        let audio_manager = AudioManager::new(
+            options.virtual_audio,
+            input_device,
+            output_device,
+            resample_quality,
+        )?;
         let voices = flite::list_voices();
 
+        let lexicon = Lexicon::load(lexicon::default_path())?;
+        lexicon.apply()?;
+
         let (tx, rx) = mpsc::channel();
 
         let gui = gui::run(tx, &voices);
+        gui.set_lexicon(lexicon.entries());
+        gui.set_hot_words(&hot_words.entries());
+        gui.set_model_path(&model_info.path);
+        gui.set_resample_quality(resample_quality);
 
         let settings = Settings {
-            voice: voices[0].to_string(),
+            voice: voices.first().ok_or(Error::NoVoices)?.to_string(),
             enable_audio: false,
+            streaming_stt: false,
+            scoring: ScoringOptions::default(),
+            decoder: options.decoder,
+            resample_quality,
+            max_recording: options.max_recording(),
+            vad: options.vad.effective(),
+            loop_preprocessing: options.loop_preprocessing.unwrap_or_default(),
+            recording_preprocessing: options.recording_preprocessing.unwrap_or_default(),
         };
+        gui.set_vad_config(&settings.vad);
+        gui.set_preprocessing(
+            &settings.loop_preprocessing,
+            &settings.recording_preprocessing,
+        );
+        match &stt_model {
+            Some(stt_model) => gui.set_decoder_config(&settings.decoder.effective(stt_model)),
+            None => gui.set_decoder_config(&settings.decoder),
+        }
 
-        Ok(TtsLooper {
+        let tts_looper = TtsLooper {
             stt_model,
+            stt_disabled_reason,
+            model_info,
             audio_manager,
+            device_selection,
             gui: Arc::new(gui),
             gui_rx: rx,
             work: LoopState::new(),
-            recording: Recording::Finished { buf: Vec::new() },
+            recording: Recording::Idle,
+            takes: Takes::default(),
+            lexicon,
+            hot_words,
+            exploration: None,
             settings,
-        })
+        };
+        tts_looper.update_devices();
+        tts_looper.update_recording_state();
+
+        Ok(tts_looper)
     }
 
     pub fn run(&mut self) {
+        self.log_startup_state();
+
         let mut loop_fn = || -> Result<(), Error> {
             loop {
-                while !self.work.is_finished() {
+                while self.blocking_action().is_some() {
                     if let Ok(req) = self.gui_rx.try_recv() {
                         if let AppState::Shutdown = self.handle_request(req)? {
                             return Ok(());
@@ -207,11 +364,27 @@ impl TtsLooper {
                         continue;
                     }
 
+                    self.process_recording()?;
                     self.iterate_work()?;
                 }
 
-                // All work is complete, sleep until more work is queued
-                let req = self.gui_rx.recv().map_err(|_| Error::GuiRecvError)?;
+                self.listen_for_speech()?;
+
+                // All work is complete, sleep until more work is queued. While
+                // recording wake up regularly to transcribe the new audio
+                let req = if self.recording.is_ongoing() {
+                    match self.gui_rx.recv_timeout(RECORDING_POLL_INTERVAL) {
+                        Ok(req) => req,
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.process_recording()?;
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return Err(Error::GuiRecvError),
+                    }
+                } else {
+                    self.gui_rx.recv().map_err(|_| Error::GuiRecvError)?
+                };
+
                 if let AppState::Shutdown = self.handle_request(req)? {
                     return Ok(());
                 }
@@ -223,6 +396,29 @@ impl TtsLooper {
         }
     }
 
+    /// Reports problems found in new, which runs before the logger exists
+    fn log_startup_state(&self) {
+        if let Some(reason) = &self.stt_disabled_reason {
+            warn!("{}", reason);
+            warn!("Speech recognition is disabled, only text to speech is available");
+        }
+
+        if !self.hot_words.entries().is_empty() && self.settings.decoder.scorer.is_none() {
+            warn!("Hot words only take effect with an external scorer");
+        }
+
+        if !self.audio_manager.has_devices() {
+            info!("Using the virtual sound card, audio devices are ignored");
+            return;
+        }
+        for direction in [Direction::Input, Direction::Output] {
+            let name = self.audio_manager.device(direction);
+            if let Err(e) = devices::find(direction, name) {
+                warn!("{}", e);
+            }
+        }
+    }
+
     fn handle_request(&mut self, req: Request) -> Result<AppState, Error> {
         match req {
             Request::Cancel => {
@@ -230,6 +426,12 @@ impl TtsLooper {
                     self.work.set_finished();
                     warn!("Canceled executing job");
                 }
+                if let Some(exploration) = &mut self.exploration {
+                    if !exploration.is_finished() {
+                        exploration.cancel();
+                        warn!("Canceled exploration");
+                    }
+                }
             }
             Request::SetVoice { voice } => {
                 self.settings.voice = voice.clone();
@@ -243,40 +445,262 @@ impl TtsLooper {
                     info!("Audio playback disabled")
                 }
             }
+            Request::EnablePhonemeScoring { enable } => {
+                self.settings.scoring.phoneme = enable;
+                if enable {
+                    info!("Phoneme scoring enabled")
+                } else {
+                    info!("Phoneme scoring disabled")
+                }
+            }
+            Request::EnableStreamingStt { enable } => {
+                self.settings.streaming_stt = enable;
+                if enable {
+                    info!("Streaming recognition enabled")
+                } else {
+                    info!("Streaming recognition disabled")
+                }
+                self.warn_streaming_preprocessing();
+            }
+            Request::SetDecoderConfig { config } => {
+                if config.alpha.is_some() != config.beta.is_some() {
+                    warn!("Alpha and beta must be set together, using scorer defaults");
+                }
+                if let Some(stt_model) = &mut self.stt_model {
+                    let default_beam_width = self.model_info.default_beam_width;
+                    if let Err(e) = config.apply(stt_model, default_beam_width) {
+                        // Leave the model in a known state before reporting
+                        let _ = self.settings.decoder.apply(stt_model, default_beam_width);
+                        let _ = self
+                            .hot_words
+                            .apply(stt_model, self.settings.decoder.scorer.is_some());
+                        return Err(e.into());
+                    }
+                }
+                info!("Decoder settings changed: {:?}", config);
+                self.settings.decoder = config;
+
+                // A newly enabled scorer starts without hot words
+                if let Some(stt_model) = &mut self.stt_model {
+                    self.hot_words
+                        .apply(stt_model, self.settings.decoder.scorer.is_some())?;
+                }
+            }
+            Request::SetVadConfig { config } => {
+                self.set_vad_config(config);
+            }
+            Request::SetLoopPreprocessing { config } => {
+                info!("Loop preprocessing set to {}", config);
+                self.settings.loop_preprocessing = config;
+                self.update_preprocessing();
+                self.warn_streaming_preprocessing();
+            }
+            Request::SetRecordingPreprocessing { config } => {
+                info!("Recording preprocessing set to {}", config);
+                self.settings.recording_preprocessing = config;
+                self.update_preprocessing();
+            }
+            Request::SetResampleQuality { quality } => {
+                self.settings.resample_quality = quality;
+                self.audio_manager.set_resample_quality(quality);
+                info!("Resample quality set to {}", quality);
+            }
+            Request::SetInputDevice { name } => {
+                self.set_device(Direction::Input, name)?;
+            }
+            Request::SetOutputDevice { name } => {
+                self.set_device(Direction::Output, name)?;
+            }
+            Request::EnableNormalizedScoring { enable } => {
+                self.settings.scoring.normalize = enable;
+                if enable {
+                    info!("Normalized scoring enabled")
+                } else {
+                    info!("Normalized scoring disabled")
+                }
+            }
+            Request::PreviewNormalization { text } => {
+                let mut normalized = Vec::new();
+                for token in flite::normalize(text, self.settings.voice.clone())? {
+                    let words = token.words.join(" ");
+                    if words != token.token {
+                        debug!("{} -> {}", token.token, words);
+                    }
+                    if !words.is_empty() {
+                        normalized.push(words);
+                    }
+                }
+                self.gui.set_normalized_text(&normalized.join(" "));
+            }
             Request::StartRecording => {
-                self.recording.start_recording(&self.audio_manager)?;
+                let result = self.recording.start(
+                    &self.audio_manager,
+                    stt_model(&mut self.stt_model)?,
+                    self.settings.resample_quality,
+                    self.settings.max_recording,
+                    None,
+                );
+                // Also resets the record controls if starting failed
+                self.update_recording_state();
+                result?;
                 info!("Recording started");
             }
             Request::EndRecording => {
-                self.recording.stop_recording();
-                info!("Recording stopped");
-                let text = self.recording_to_text()?;
-                info!("Recorded text: {}", text);
-                self.gui.push_input_text(&text);
+                if self.recording.state() == RecordingState::Listening {
+                    // Otherwise listening would start again right away
+                    self.set_vad_config(VadConfig {
+                        enabled: Some(false),
+                        ..self.settings.vad.clone()
+                    });
+                } else {
+                    self.end_recording()?;
+                }
+            }
+            Request::PauseRecording => {
+                self.process_recording()?;
+                self.recording.set_paused(true)?;
+                self.update_recording_state();
+                info!("Recording paused");
+            }
+            Request::ResumeRecording => {
+                self.process_recording()?;
+                self.recording.set_paused(false)?;
+                self.update_recording_state();
+                info!("Recording resumed");
+            }
+            Request::PlayTake { take } => {
+                let take = self.takes.get(take)?;
+                self.audio_manager
+                    .play_buf_blocking(&take.audio, take.sample_rate)?;
+            }
+            Request::RenameTake { take, name } => {
+                self.takes.rename(take, &name)?;
+                self.update_takes();
+                info!("Take renamed to {}", name.trim());
+            }
+            Request::ExportTake { take, path } => {
+                let take = self.takes.get(take)?;
+                take.export(path.clone())?;
+                info!("{} exported to {}", take.name, path.display());
+            }
+            Request::TranscribeTake { take } => {
+                let recognition = self.recognize_take(take)?;
+                self.set_take_recognition(take, recognition)?;
+            }
+            Request::RemoveTake { take } => {
+                let take = self.takes.remove(take)?;
+                self.update_takes();
+                info!("{} removed", take.name);
+            }
+            Request::LoopTake { take, num_iters } => {
+                self.start_take_loop(take, iterations(num_iters)?)?;
             }
             Request::Save { path } => {
                 self.save_full_wav(&path)?;
             }
-            Request::TtsLoop { text, num_iters } => {
-                if !self.work.is_finished() {
+            Request::SetLexiconEntry { word, phones } => {
+                self.lexicon.set_entry(&word, &phones)?;
+                self.update_lexicon()?;
+                info!("Lexicon entry set: {} -> {}", word, phones);
+            }
+            Request::RemoveLexiconEntry { word } => {
+                self.lexicon.remove_entry(&word)?;
+                self.update_lexicon()?;
+                info!("Lexicon entry removed: {}", word);
+            }
+            Request::SetHotWord { word, boost } => {
+                self.hot_words.set_entry(&word, &boost)?;
+                self.update_hot_words()?;
+                info!("Hot word set: {} -> {}", word, boost);
+                if self.settings.decoder.scorer.is_none() {
+                    warn!("Hot words only take effect with an external scorer");
+                }
+            }
+            Request::RemoveHotWord { word } => {
+                self.hot_words.remove_entry(&word)?;
+                self.update_hot_words()?;
+                info!("Hot word removed: {}", word);
+            }
+            Request::PreviewWord { word } => {
+                let wav = flite::text_to_wave(word, self.settings.voice.clone())?;
+                self.audio_manager
+                    .play_buf_blocking(&wav, wav.sample_rate() as u32)?;
+            }
+            Request::SetModel { path } => {
+                if let Some(blocking_action) = self.blocking_action() {
                     return Err(Error::Busy {
-                        attempted_action: "tts loop".to_string(),
-                        blocking_action: "tts loop".to_string(),
+                        attempted_action: "model switch".to_string(),
+                        blocking_action: blocking_action.to_string(),
                     });
                 }
+                // Listening starts again with the new model
+                self.stop_listening();
+                if self.recording.is_ongoing() {
+                    return Err(Error::CurrentlyRecording);
+                }
+                if self.stt_model.is_none() {
+                    return Err(Error::SttUnavailable);
+                }
+
+                let (stt_model, model_info) =
+                    load_stt_model(&path, &self.settings.decoder, &self.hot_words)?;
+                self.stt_model = Some(stt_model);
+                self.model_info = model_info;
+                self.gui.set_model_path(&self.model_info.path);
+                info!("Model changed: {}", self.model_info.path.display());
+            }
+            Request::Explore { text, budget } => {
+                if let Some(blocking_action) = self.blocking_action() {
+                    return Err(Error::Busy {
+                        attempted_action: "exploration".to_string(),
+                        blocking_action: blocking_action.to_string(),
+                    });
+                }
+                self.stop_listening();
+
+                let decoder = self
+                    .settings
+                    .decoder
+                    .effective(stt_model(&mut self.stt_model)?);
 
                 info!(
-                    "Starting work. Text: {}, Number of iterations: {}",
-                    text, num_iters
+                    "Starting exploration. Text: {}, Branching: {}, Depth: {}, Nodes: {}",
+                    text, budget.branching, budget.max_depth, budget.max_nodes
                 );
 
-                self.work = LoopState {
-                    phase: LoopStatePhase::Tts,
-                    text,
-                    wav: Vec::new(),
-                    last_frame_len: 0,
-                    remaining_iters: num_iters.try_into().unwrap(),
+                let reference = Reference::new(&text, self.settings.scoring, &self.settings.voice)?;
+                let hot_words = self.hot_words.active(decoder.scorer.is_some());
+                let tree = ExplorationTree {
+                    input: text,
+                    voice: self.settings.voice.clone(),
+                    scoring: self.settings.scoring,
+                    model: self.model_info.clone(),
+                    decoder,
+                    hot_words,
+                    budget,
+                    nodes: Vec::new(),
                 };
+                let exploration = Exploration::new(tree, reference)?;
+                self.gui.set_exploration_tree(exploration.tree());
+                self.exploration = Some(exploration);
+            }
+            Request::SaveExploration { path } => {
+                let exploration = self.exploration.as_ref().ok_or(Error::NoData)?;
+                if !exploration.is_finished() {
+                    return Err(Error::Busy {
+                        attempted_action: "save exploration".to_string(),
+                        blocking_action: "exploration".to_string(),
+                    });
+                }
+
+                exploration
+                    .tree()
+                    .save(&path)
+                    .map_err(Error::ResultsWriteError)?;
+                info!("Exploration saved to {}", path.display());
+            }
+            Request::TtsLoop { text, num_iters } => {
+                self.start_loop(text, iterations(num_iters)?)?;
             }
             Request::Shutdown => {
                 return Ok(AppState::Shutdown);
@@ -286,18 +710,134 @@ impl TtsLooper {
         Ok(AppState::Running)
     }
 
+    /// Starts running text through the loop num_iters times
+    fn start_loop(&mut self, text: String, num_iters: usize) -> Result<(), Error> {
+        if let Some(blocking_action) = self.blocking_action() {
+            return Err(Error::Busy {
+                attempted_action: "tts loop".to_string(),
+                blocking_action: blocking_action.to_string(),
+            });
+        }
+        // Playback would be taken for speech. Listening starts again once the
+        // loop is done
+        self.stop_listening();
+
+        let decoder = self
+            .settings
+            .decoder
+            .effective(stt_model(&mut self.stt_model)?);
+
+        info!(
+            "Starting work. Text: {}, Number of iterations: {}",
+            text, num_iters
+        );
+
+        let reference = Reference::new(&text, self.settings.scoring, &self.settings.voice)?;
+        let hot_words = self.hot_words.active(decoder.scorer.is_some());
+
+        self.work = LoopState {
+            phase: LoopStatePhase::Tts,
+            results: RunResults::new(
+                text.clone(),
+                self.settings.voice.clone(),
+                self.settings.scoring,
+                self.model_info.clone(),
+                decoder,
+                hot_words,
+            ),
+            text,
+            wav: Vec::new(),
+            last_frame_len: 0,
+            remaining_iters: num_iters,
+            reference,
+            playback: None,
+            stt_stream: None,
+        };
+
+        Ok(())
+    }
+
+    /// Name of the job currently running, if any
+    fn blocking_action(&self) -> Option<&'static str> {
+        if !self.work.is_finished() {
+            return Some("tts loop");
+        }
+
+        match &self.exploration {
+            Some(exploration) if !exploration.is_finished() => Some("exploration"),
+            _ => None,
+        }
+    }
+
+    fn iterate_exploration(&mut self) -> Result<(), Error> {
+        let exploration = match &mut self.exploration {
+            Some(exploration) => exploration,
+            None => return Ok(()),
+        };
+
+        let resample_quality = self.settings.resample_quality;
+        let result = stt_model(&mut self.stt_model).and_then(|stt_model| {
+            exploration.step(stt_model, resample_quality)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            exploration.cancel();
+            return Err(e);
+        }
+
+        let tree = exploration.tree();
+        self.gui.set_exploration_tree(tree);
+        if exploration.is_finished() {
+            info!(
+                "Exploration complete: {} nodes, {} distinct outcomes",
+                tree.nodes.len(),
+                tree.distinct_leaves()
+            );
+        }
+
+        Ok(())
+    }
+
     fn iterate_work(&mut self) -> Result<(), Error> {
+        if self.work.is_finished() {
+            return self.iterate_exploration();
+        }
+
         self.work.phase = match self.work.phase {
             LoopStatePhase::Playback => {
-                if self.settings.enable_audio {
-                    self.audio_manager
-                        .play_buf_blocking(self.work.last_frame(), SAMPLE_RATE)?;
+                // A failed playback is reported but the loop carries on with
+                // recognition. The next iteration opens the device again
+                if let Some(playback) = self.work.playback.take() {
+                    playback.finish_blocking()?;
                 }
                 LoopStatePhase::Stt
             }
             LoopStatePhase::Stt => {
-                self.work.text = self.stt_model.speech_to_text(self.work.last_frame())?;
-                self.gui.push_output(&self.work.text);
+                let recognition = match self.work.stt_stream.take() {
+                    Some(stt_stream) if stt_stream.is_usable() => stt_stream.finish()?,
+                    _ => {
+                        let sample_rate = self.work.results.model.sample_rate as u32;
+                        let audio = self
+                            .settings
+                            .loop_preprocessing
+                            .apply(self.work.last_frame(), sample_rate);
+                        recognition::recognize(stt_model(&mut self.stt_model)?, &audio)?
+                    }
+                };
+                self.work.text = recognition.text().to_string();
+                self.gui.push_intermediate_text("");
+                self.gui.push_recognition(&recognition);
+
+                let scores = self
+                    .work
+                    .reference
+                    .score(&self.work.text, &self.work.results.voice)?;
+                self.gui.push_scores(&scores);
+                self.work.results.iterations.push(Iteration {
+                    text: self.work.text.clone(),
+                    scores,
+                    recognition,
+                });
 
                 self.work.remaining_iters = self.work.remaining_iters.saturating_sub(1);
                 if self.work.remaining_iters == 0 {
@@ -308,11 +848,18 @@ impl TtsLooper {
                 }
             }
             LoopStatePhase::Tts => {
-                let wav = flite::text_to_wave(
-                    self.work.text.clone(),
-                    SAMPLE_RATE as i32,
-                    self.settings.voice.clone(),
-                )?;
+                // Recognized at the rate of the model the loop started with
+                let sample_rate = self.work.results.model.sample_rate as u32;
+                let wav = if self.settings.enable_audio || self.settings.streaming_stt {
+                    self.synthesize_streaming(sample_rate)?
+                } else {
+                    synthesize(
+                        self.work.text.clone(),
+                        self.settings.voice.clone(),
+                        sample_rate,
+                        self.settings.resample_quality,
+                    )?
+                };
                 self.work.last_frame_len = wav.len();
                 self.work.wav.extend(wav.iter());
                 LoopStatePhase::Playback
@@ -323,15 +870,370 @@ impl TtsLooper {
         Ok(())
     }
 
-    fn recording_to_text(&mut self) -> Result<String, Error> {
-        let buf = match &self.recording {
-            Recording::Finished { buf } => buf,
-            Recording::Ongoing { .. } => {
-                return Err(Error::CurrentlyRecording);
+    /// Synthesizes the current text, handing audio to playback and/or
+    /// recognition as it is produced. Both are left running in self.work for
+    /// the following phases to finish. Returns the audio at sample_rate
+    fn synthesize_streaming(&mut self, sample_rate: u32) -> Result<Vec<i16>, Error> {
+        let audio_manager = &self.audio_manager;
+        let gui = &self.gui;
+        let mut enable_audio = self.settings.enable_audio;
+
+        let mut playback: Option<StreamingPlayback> = None;
+        // Preprocessing needs the whole frame, so recognition waits for it
+        let streaming_stt =
+            self.settings.streaming_stt && !self.settings.loop_preprocessing.is_active();
+        let mut stt_stream = if streaming_stt {
+            Some(SttStream::new(
+                stt_model(&mut self.stt_model)?,
+                self.settings.resample_quality,
+            )?)
+        } else {
+            None
+        };
+        let mut stream_err: Option<Error> = None;
+
+        let wav = flite::text_to_wave_streaming(
+            self.work.text.clone(),
+            self.settings.voice.clone(),
+            |chunk, chunk_rate| {
+                // Chunks come at the voice's rate, which is only known once the
+                // first one arrives
+                if enable_audio && playback.is_none() {
+                    match audio_manager.start_playback(chunk_rate as u32) {
+                        Ok(p) => playback = Some(p),
+                        Err(e) => {
+                            // Recognition doesn't need the device, keep going
+                            error!("{}", e);
+                            warn!("Continuing this iteration without playback");
+                            enable_audio = false;
+                        }
+                    }
+                }
+
+                if let Some(playback) = &mut playback {
+                    playback.push(chunk);
+                }
+
+                if let Some(stt_stream) = &mut stt_stream {
+                    match stt_stream.feed(chunk, chunk_rate as u32) {
+                        Ok(Some(text)) => gui.push_intermediate_text(&text),
+                        Ok(None) => (),
+                        Err(e) => {
+                            stream_err = Some(e.into());
+                            return false;
+                        }
+                    }
+                }
+
+                true
+            },
+        )?;
+
+        if let Some(e) = stream_err {
+            return Err(e);
+        }
+
+        if let Some(stt_stream) = &stt_stream {
+            if !stt_stream.is_usable() {
+                warn!("Voice sample rate changed during synthesis, recognizing afterwards");
+            }
+        }
+
+        self.work.playback = playback;
+        self.work.stt_stream = stt_stream;
+        Ok(resample::resample(
+            &wav,
+            wav.sample_rate() as u32,
+            sample_rate,
+            self.settings.resample_quality,
+        ))
+    }
+
+    /// Switches to the device called name, or the system default if None, and
+    /// remembers the choice
+    fn set_device(&mut self, direction: Direction, name: Option<String>) -> Result<(), Error> {
+        if let Err(e) = devices::find(direction, name.as_deref()) {
+            // Show the device still in use, and any that appeared
+            self.update_devices();
+            return Err(e.into());
+        }
+
+        self.audio_manager.set_device(direction, name.clone());
+        if direction == Direction::Input {
+            // Listening starts again on the new device
+            self.stop_listening();
+        }
+        self.device_selection.set(direction, name.clone());
+        self.device_selection.save()?;
+        self.update_devices();
+
+        match name {
+            Some(name) => info!("Audio {} device set to {}", direction, name),
+            None => info!("Audio {} device set to the system default", direction),
+        }
+        Ok(())
+    }
+
+    fn update_devices(&self) {
+        self.gui.set_audio_devices(
+            &devices::device_names(Direction::Input),
+            &devices::device_names(Direction::Output),
+            self.audio_manager.device(Direction::Input),
+            self.audio_manager.device(Direction::Output),
+        );
+    }
+
+    fn update_lexicon(&mut self) -> Result<(), Error> {
+        self.lexicon.apply()?;
+        self.lexicon.save()?;
+        self.gui.set_lexicon(self.lexicon.entries());
+        Ok(())
+    }
+
+    fn update_hot_words(&mut self) -> Result<(), Error> {
+        if let Some(stt_model) = &mut self.stt_model {
+            self.hot_words
+                .apply(stt_model, self.settings.decoder.scorer.is_some())?;
+        }
+        self.hot_words.save()?;
+        self.gui.set_hot_words(&self.hot_words.entries());
+        Ok(())
+    }
+
+    fn process_recording(&mut self) -> Result<(), Error> {
+        if !self.recording.is_ongoing() {
+            return Ok(());
+        }
+
+        if self.recording.reconnect(&self.audio_manager) {
+            info!("Input device is available again, recording resumed");
+        }
+
+        let prev_state = self.recording.state();
+        match self.recording.process() {
+            Ok(Some(text)) => self.gui.push_input_text(&text),
+            Ok(None) => (),
+            Err(e) => {
+                // The device may have disappeared from the pickers
+                self.update_devices();
+                return Err(e.into());
             }
+        }
+        self.update_recording_state();
+
+        if prev_state == RecordingState::Listening
+            && self.recording.state() == RecordingState::Recording
+        {
+            info!("Speech detected, recording");
+        }
+
+        if self.recording.reached_limit() {
+            if let Some(max_recording) = self.settings.max_recording {
+                warn!(
+                    "Recording reached the {:.0}s limit",
+                    max_recording.as_secs_f32()
+                );
+            }
+            self.end_recording()?;
+        } else if self.recording.speech_ended() {
+            info!("End of speech");
+            let text = self.end_recording()?;
+
+            let num_iters = self.settings.vad.loop_iters();
+            if num_iters > 0 && !text.is_empty() {
+                self.start_loop(text, num_iters as usize)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts listening for speech if voice activation is enabled and nothing
+    /// else is going on. Voice activation is disabled if listening fails
+    fn listen_for_speech(&mut self) -> Result<(), Error> {
+        if !self.settings.vad.enabled()
+            || self.recording.is_ongoing()
+            || self.blocking_action().is_some()
+        {
+            return Ok(());
+        }
+
+        let result = match &mut self.stt_model {
+            Some(stt_model) => self
+                .recording
+                .start(
+                    &self.audio_manager,
+                    stt_model,
+                    self.settings.resample_quality,
+                    self.settings.max_recording,
+                    Some(&self.settings.vad),
+                )
+                .map_err(Error::from),
+            None => Err(Error::SttUnavailable),
         };
+        self.update_recording_state();
+
+        if let Err(e) = result {
+            self.set_vad_config(VadConfig {
+                enabled: Some(false),
+                ..self.settings.vad.clone()
+            });
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stops a voice activated recording that hasn't heard speech yet
+    fn stop_listening(&mut self) {
+        if self.recording.state() == RecordingState::Listening {
+            self.recording.discard();
+            self.update_recording_state();
+        }
+    }
+
+    fn set_vad_config(&mut self, config: VadConfig) {
+        let config = config.effective();
+        // Listening starts again with the new settings if still enabled
+        self.stop_listening();
+
+        if config.enabled() != self.settings.vad.enabled() {
+            if config.enabled() {
+                info!("Voice activation enabled")
+            } else {
+                info!("Voice activation disabled")
+            }
+        }
+        info!(
+            "Voice activation settings: sensitivity {:.2}, hang-over {:.1}s, {} loop iterations",
+            config.sensitivity(),
+            config.hangover().as_secs_f32(),
+            config.loop_iters()
+        );
+
+        self.settings.vad = config;
+        self.gui.set_vad_config(&self.settings.vad);
+    }
+
+    /// Stops recording once the input device has delivered everything, keeps
+    /// the recording as a take and transcribes it. Returns the transcript
+    fn end_recording(&mut self) -> Result<String, Error> {
+        let stopped = self.recording.stop();
+        self.update_recording_state();
+        let stopped = stopped?;
+        info!("Recording stopped");
+
+        let take = self.takes.add(stopped.audio, stopped.sample_rate);
+        self.update_takes();
+
+        // The stream only saw the audio as it was recorded
+        let stt_stream = stopped
+            .stt_stream
+            .filter(|_| !self.settings.recording_preprocessing.is_active());
+        let recognition = match stt_stream {
+            Some(stt_stream) => stt_stream.finish()?,
+            None => self.recognize_take(take)?,
+        };
+        self.set_take_recognition(take, recognition)
+    }
+
+    /// Recognizes a take with the current model, which may not be the one it
+    /// was recorded for
+    fn recognize_take(&mut self, id: u32) -> Result<Recognition, Error> {
+        let take = self.takes.get(id)?;
+        let stt_model = stt_model(&mut self.stt_model)?;
+        let sample_rate = stt_model.get_sample_rate() as u32;
 
-        Ok(self.stt_model.speech_to_text(buf)?)
+        let audio = resample::resample(
+            &take.audio,
+            take.sample_rate,
+            sample_rate,
+            self.settings.resample_quality,
+        );
+        let audio = self
+            .settings
+            .recording_preprocessing
+            .apply(&audio, sample_rate);
+        Ok(recognition::recognize(stt_model, &audio)?)
+    }
+
+    /// Keeps recognition as the transcript of a take and puts it in the text
+    /// box. Returns the transcript
+    fn set_take_recognition(&mut self, id: u32, recognition: Recognition) -> Result<String, Error> {
+        let take = self.takes.get_mut(id)?;
+        let text = recognition.text().to_string();
+        info!("{}: {}", take.name, text);
+        take.recognition = Some(recognition);
+
+        self.gui.push_input_text(&text);
+        self.update_takes();
+        Ok(text)
+    }
+
+    /// Starts the loop from the transcript of a take, with the take itself as
+    /// iteration 0 so that it is part of the saved wav and results
+    fn start_take_loop(&mut self, id: u32, num_iters: usize) -> Result<(), Error> {
+        let recognition = match self.takes.get(id)?.recognition.clone() {
+            Some(recognition) => recognition,
+            None => {
+                let recognition = self.recognize_take(id)?;
+                self.set_take_recognition(id, recognition.clone())?;
+                recognition
+            }
+        };
+        let text = recognition.text().to_string();
+        self.start_loop(text.clone(), num_iters)?;
+
+        let scores = match self.work.reference.score(&text, &self.work.results.voice) {
+            Ok(scores) => scores,
+            Err(e) => {
+                self.work.set_finished();
+                return Err(e.into());
+            }
+        };
+        self.gui.push_recognition(&recognition);
+        self.gui.push_scores(&scores);
+
+        let take = self.takes.get(id)?;
+        let audio = resample::resample(
+            &take.audio,
+            take.sample_rate,
+            self.work.results.model.sample_rate as u32,
+            self.settings.resample_quality,
+        );
+        self.work.results.take = Some(take.name.clone());
+        self.work.results.iterations.push(Iteration {
+            text,
+            scores,
+            recognition,
+        });
+        self.work.last_frame_len = audio.len();
+        self.work.wav = audio;
+        Ok(())
+    }
+
+    fn update_takes(&self) {
+        self.gui.set_takes(self.takes.entries());
+    }
+
+    fn update_recording_state(&self) {
+        self.gui.set_recording_state(
+            self.recording.state(),
+            self.recording.elapsed(),
+            self.settings.max_recording,
+        );
+    }
+
+    fn update_preprocessing(&self) {
+        self.gui.set_preprocessing(
+            &self.settings.loop_preprocessing,
+            &self.settings.recording_preprocessing,
+        );
+    }
+
+    fn warn_streaming_preprocessing(&self) {
+        if self.settings.streaming_stt && self.settings.loop_preprocessing.is_active() {
+            warn!("The loop recognizes after synthesis while its audio is preprocessed");
+        }
     }
 
     fn save_full_wav<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
@@ -347,16 +1249,61 @@ impl TtsLooper {
 
         let wav_spec = WavSpec {
             channels: 1,
-            sample_rate: SAMPLE_RATE,
+            sample_rate: self.work.results.model.sample_rate as u32,
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         };
 
-        let mut writer = WavWriter::create(path, wav_spec).unwrap();
+        let mut writer = WavWriter::create(&path, wav_spec).unwrap();
         for sample in full_wav {
             writer.write_sample(*sample).map_err(Error::WavWriteError)?;
         }
 
+        self.work
+            .results
+            .save(path.as_ref().with_extension("json"))
+            .map_err(Error::ResultsWriteError)?;
+
         Ok(())
     }
 }
+
+/// Number of loop iterations requested by the gui, which can't be negative
+fn iterations(num_iters: i32) -> Result<usize, Error> {
+    num_iters
+        .try_into()
+        .map_err(|_| Error::InvalidIterations(num_iters))
+}
+
+/// The loaded model, or an error if recognition is disabled
+fn stt_model(stt_model: &mut Option<DsModel>) -> Result<&mut DsModel, Error> {
+    stt_model.as_mut().ok_or(Error::SttUnavailable)
+}
+
+/// Synthesizes text with voice and converts it to sample_rate
+pub(crate) fn synthesize(
+    text: String,
+    voice: String,
+    sample_rate: u32,
+    resample_quality: ResampleQuality,
+) -> Result<Vec<i16>, flite::Error> {
+    let wav = flite::text_to_wave(text, voice)?;
+    Ok(resample::resample(
+        &wav,
+        wav.sample_rate() as u32,
+        sample_rate,
+        resample_quality,
+    ))
+}
+
+/// Loads the model at path with the decoder settings and hot words applied
+fn load_stt_model(
+    path: &Path,
+    decoder: &DecoderConfig,
+    hot_words: &HotWords,
+) -> Result<(DsModel, ModelInfo), Error> {
+    let (mut stt_model, model_info) = model::load(path)?;
+    decoder.apply(&mut stt_model, model_info.default_beam_width)?;
+    hot_words.apply(&mut stt_model, decoder.scorer.is_some())?;
+    Ok((stt_model, model_info))
+}
@@ -0,0 +1,177 @@
+use deepspeech::{errors::DeepspeechError, Model as DsModel};
+use deepspeech_sys::known_files::{self, KnownFile, VerifyError};
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// File looked up in each library search directory
+#[cfg(feature = "runtime-deepspeech")]
+const LIBRARY_NAME: &str = "libdeepspeech.so";
+
+/// Models stored with Git LFS in this repository
+const KNOWN_MODELS: &[KnownFile] = &[KnownFile {
+    name: "deepspeech-0.9.3-models.tflite",
+    size: 47331784,
+    sha256: "0a88f98ff15c9bf760bf7da035b9dafae240e7eb000af376f87e052aae331203",
+}];
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Failed to read {path}: {err}")]
+    Read { path: PathBuf, err: io::Error },
+    #[error("Failed to load model {path}: {err}")]
+    Load { path: PathBuf, err: DeepspeechError },
+    #[error(
+        "{0} is a Git LFS pointer, not the file itself. Install git-lfs and run `git lfs \
+         pull` to fetch it"
+    )]
+    LfsPointer(PathBuf),
+    #[error(
+        "{path} is {actual} bytes, expected {expected}. The download may be incomplete, \
+         run `git lfs pull` to fetch it again"
+    )]
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+    #[error(
+        "{path} has checksum {actual}, expected {expected}. The file may be corrupt, run \
+         `git lfs pull` to fetch it again"
+    )]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[cfg(feature = "runtime-deepspeech")]
+    #[error("Failed to load {path}: {err}")]
+    Library {
+        path: PathBuf,
+        err: deepspeech_sys::LoadError,
+    },
+    #[cfg(feature = "runtime-deepspeech")]
+    #[error("libdeepspeech not found, searched: {0}")]
+    LibraryNotFound(String),
+}
+
+/// Identifies the model a run was recognized with
+#[derive(Serialize, Clone, Debug, Default)]
+pub(crate) struct ModelInfo {
+    pub(crate) path: PathBuf,
+    pub(crate) file_size: u64,
+    pub(crate) sha256: String,
+    pub(crate) sample_rate: i32,
+    /// Beam width the model file asks for, used while none is configured
+    pub(crate) default_beam_width: u16,
+}
+
+/// Loads the model at path after checking it is the real file. Models from
+/// this repository are also checked against their known size and checksum
+pub(crate) fn load(path: &Path) -> Result<(DsModel, ModelInfo), Error> {
+    let (file_size, sha256) = verify(path, KNOWN_MODELS)?;
+
+    let model = DsModel::load_from_files(path).map_err(|err| Error::Load {
+        path: path.to_path_buf(),
+        err,
+    })?;
+
+    let info = ModelInfo {
+        path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+        file_size,
+        sha256,
+        sample_rate: model.get_sample_rate(),
+        default_beam_width: model.get_model_beam_width(),
+    };
+
+    Ok((model, info))
+}
+
+/// Loads libdeepspeech from the first of search_paths that has it, then from
+/// next to the executable, then from the system library path. Directories are
+/// searched for LIBRARY_NAME
+#[cfg(feature = "runtime-deepspeech")]
+pub(crate) fn load_library(search_paths: &[PathBuf]) -> Result<(), Error> {
+    use deepspeech_sys::LoadError;
+
+    let mut candidates = search_paths
+        .iter()
+        .map(|p| {
+            if p.is_dir() {
+                p.join(LIBRARY_NAME)
+            } else {
+                p.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        candidates.push(exe_dir.join(LIBRARY_NAME));
+    }
+    candidates.push(PathBuf::from(LIBRARY_NAME));
+
+    for path in &candidates {
+        // A pointer would only fail to open, and be reported as not found. A bare
+        // name is looked up in the system library path, not in this directory
+        let is_file_path = path.parent().map_or(false, |p| !p.as_os_str().is_empty());
+        if is_file_path {
+            match verify(path, known_files::KNOWN_LIBRARIES) {
+                Ok(_) => (),
+                Err(Error::Read { err, .. }) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        match deepspeech_sys::load_library(path) {
+            Ok(()) => return Ok(()),
+            // Not there, try the next one
+            Err(LoadError::Open(_)) => continue,
+            Err(err) => {
+                return Err(Error::Library {
+                    path: path.clone(),
+                    err,
+                })
+            }
+        }
+    }
+
+    let searched = candidates
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>();
+    Err(Error::LibraryNotFound(searched.join(", ")))
+}
+
+/// libdeepspeech is linked at build time, so there is nothing to load
+#[cfg(not(feature = "runtime-deepspeech"))]
+pub(crate) fn load_library(_search_paths: &[PathBuf]) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Checks that path is not a Git LFS pointer, and that files in known match
+/// their size and checksum. Returns the size and checksum of the file
+fn verify(path: &Path, known: &[KnownFile]) -> Result<(u64, String), Error> {
+    known_files::verify(path, known).map_err(|e| {
+        let path = path.to_path_buf();
+        match e {
+            VerifyError::Read(err) => Error::Read { path, err },
+            VerifyError::LfsPointer => Error::LfsPointer(path),
+            VerifyError::SizeMismatch { expected, actual } => Error::SizeMismatch {
+                path,
+                expected,
+                actual,
+            },
+            VerifyError::ChecksumMismatch { expected, actual } => Error::ChecksumMismatch {
+                path,
+                expected: expected.to_string(),
+                actual,
+            },
+        }
+    })
+}
@@ -0,0 +1,554 @@
+//! Cleanup of audio before it is recognized. Every step is optional, they run
+//! in the order DC removal, noise reduction, trimming, normalization, padding
+
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::PI;
+
+/// Cutoff of the high pass filter that removes DC, in Hz
+const DC_CUTOFF: f64 = 20.0;
+
+/// Frames levels are measured over when trimming and normalizing
+const FRAME_SECS: f64 = 0.02;
+
+/// Frames this far below the loudest one are silence, in dB
+const TRIM_THRESHOLD: f64 = 40.0;
+/// Frames quieter than this are always silence, in dBFS
+const SILENCE_LEVEL: f64 = -55.0;
+/// Silence kept around what is trimmed to, so that soft onsets and endings
+/// survive
+const TRIM_MARGIN_SECS: f64 = 0.05;
+
+/// Targets of peak and loudness normalization, in dBFS
+const PEAK_TARGET: f64 = -1.0;
+const LOUDNESS_TARGET: f64 = -20.0;
+
+/// Length of the frames noise is removed from, rounded up to a power of two
+const FFT_SECS: f64 = 0.032;
+/// Share of the quietest frames the noise spectrum is estimated from
+const NOISE_FRACTION: f64 = 0.1;
+/// How many times the noise estimate is subtracted, to also remove the noise
+/// that lies above the average
+const OVER_SUBTRACTION: f64 = 2.0;
+/// Lowest gain of a frequency bin, which keeps some noise to avoid the
+/// warbling of bins switching on and off
+const SPECTRAL_FLOOR: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    /// Scales the loudest sample to PEAK_TARGET
+    Peak,
+    /// Scales the RMS level of the non silent frames to LOUDNESS_TARGET,
+    /// without clipping
+    Loudness,
+}
+
+impl std::str::FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Normalization, String> {
+        match s {
+            "peak" => Ok(Normalization::Peak),
+            "loudness" => Ok(Normalization::Loudness),
+            _ => Err(format!(
+                "Invalid normalization \"{}\", expected peak or loudness",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Normalization::Peak => "peak",
+            Normalization::Loudness => "loudness",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Steps applied to audio before recognition. The default changes nothing
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preprocessing {
+    /// Removes any DC offset of the microphone
+    pub remove_dc: bool,
+    /// Reduces steady background noise by spectral subtraction
+    pub denoise: bool,
+    /// Cuts leading and trailing silence
+    pub trim: bool,
+    /// Seconds of silence added to both ends
+    pub pad_secs: f32,
+    pub normalize: Option<Normalization>,
+}
+
+/// Written as the comma separated steps, e.g. `dc,denoise,trim,pad=0.2,peak`,
+/// or `none`
+impl std::str::FromStr for Preprocessing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Preprocessing, String> {
+        let mut preprocessing = Preprocessing::default();
+
+        for step in s.split(',').map(str::trim) {
+            match step {
+                "" | "none" => (),
+                "dc" => preprocessing.remove_dc = true,
+                "denoise" => preprocessing.denoise = true,
+                "trim" => preprocessing.trim = true,
+                _ => {
+                    if let Some(secs) = step.strip_prefix("pad=") {
+                        preprocessing.pad_secs = match secs.parse() {
+                            Ok(secs) if secs >= 0.0 => secs,
+                            _ => return Err(format!("Invalid padding \"{}\"", secs)),
+                        };
+                    } else {
+                        let normalize = step.parse().map_err(|_| {
+                            format!(
+                                "Invalid preprocessing step \"{}\", expected dc, denoise, trim, \
+                                 pad=<secs>, peak or loudness",
+                                step
+                            )
+                        })?;
+                        preprocessing.normalize = Some(normalize);
+                    }
+                }
+            }
+        }
+
+        Ok(preprocessing)
+    }
+}
+
+impl std::fmt::Display for Preprocessing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut steps = Vec::new();
+        if self.remove_dc {
+            steps.push("dc".to_string());
+        }
+        if self.denoise {
+            steps.push("denoise".to_string());
+        }
+        if self.trim {
+            steps.push("trim".to_string());
+        }
+        if self.pad_secs > 0.0 {
+            steps.push(format!("pad={}", self.pad_secs));
+        }
+        if let Some(normalize) = self.normalize {
+            steps.push(normalize.to_string());
+        }
+
+        if steps.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", steps.join(","))
+        }
+    }
+}
+
+impl Preprocessing {
+    /// Whether apply changes anything
+    pub(crate) fn is_active(&self) -> bool {
+        *self != Preprocessing::default()
+    }
+
+    /// samples at sample_rate with every enabled step applied
+    pub(crate) fn apply(&self, samples: &[i16], sample_rate: u32) -> Vec<i16> {
+        if !self.is_active() {
+            return samples.to_vec();
+        }
+
+        let mut buf = samples
+            .iter()
+            .map(|&s| s as f64 / i16::MAX as f64)
+            .collect::<Vec<_>>();
+
+        if self.remove_dc {
+            remove_dc(&mut buf, sample_rate);
+        }
+        if self.denoise {
+            buf = denoise(&buf, sample_rate);
+        }
+        if self.trim {
+            buf = trim(&buf, sample_rate).to_vec();
+        }
+        match self.normalize {
+            Some(Normalization::Peak) => normalize_peak(&mut buf),
+            Some(Normalization::Loudness) => normalize_loudness(&mut buf, sample_rate),
+            None => (),
+        }
+
+        let pad_len = if self.pad_secs.is_finite() && self.pad_secs > 0.0 {
+            (self.pad_secs as f64 * sample_rate as f64) as usize
+        } else {
+            0
+        };
+        let padding = std::iter::repeat(0).take(pad_len);
+
+        padding
+            .clone()
+            .chain(buf.iter().map(|&s| {
+                (s * i16::MAX as f64)
+                    .round()
+                    .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+            }))
+            .chain(padding)
+            .collect()
+    }
+}
+
+fn to_db(x: f64) -> f64 {
+    if x > 0.0 {
+        20.0 * x.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+fn from_db(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+fn rms(samples: &[f64]) -> f64 {
+    let power = samples.iter().map(|s| s * s).sum::<f64>() / samples.len().max(1) as f64;
+    power.sqrt()
+}
+
+fn frame_len(sample_rate: u32) -> usize {
+    ((sample_rate as f64 * FRAME_SECS) as usize).max(1)
+}
+
+/// Which frames of FRAME_SECS hold more than silence
+fn loud_frames(buf: &[f64], sample_rate: u32) -> Vec<bool> {
+    let levels = buf
+        .chunks(frame_len(sample_rate))
+        .map(|frame| to_db(rms(frame)))
+        .collect::<Vec<_>>();
+    let loudest = levels.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let threshold = (loudest - TRIM_THRESHOLD).max(SILENCE_LEVEL);
+
+    levels.iter().map(|&level| level >= threshold).collect()
+}
+
+/// One pole high pass filter at DC_CUTOFF
+fn remove_dc(buf: &mut [f64], sample_rate: u32) {
+    let r = (-2.0 * PI * DC_CUTOFF / sample_rate as f64).exp();
+    let mut prev_in = 0.0;
+    let mut prev_out = 0.0;
+
+    for sample in buf {
+        let out = *sample - prev_in + r * prev_out;
+        prev_in = *sample;
+        prev_out = out;
+        *sample = out;
+    }
+}
+
+/// buf without leading and trailing silence, short of a TRIM_MARGIN_SECS
+/// margin. All of buf if it is silent
+fn trim(buf: &[f64], sample_rate: u32) -> &[f64] {
+    let frame_len = frame_len(sample_rate);
+    let loud = loud_frames(buf, sample_rate);
+
+    let (first, last) = match (loud.iter().position(|&l| l), loud.iter().rposition(|&l| l)) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return buf,
+    };
+
+    let margin = (TRIM_MARGIN_SECS * sample_rate as f64) as usize;
+    let start = (first * frame_len).saturating_sub(margin);
+    let end = ((last + 1) * frame_len + margin).min(buf.len());
+    &buf[start..end]
+}
+
+fn normalize_peak(buf: &mut [f64]) {
+    let peak = buf.iter().map(|s| s.abs()).fold(0.0, f64::max);
+    if peak > 0.0 {
+        let gain = from_db(PEAK_TARGET) / peak;
+        buf.iter_mut().for_each(|s| *s *= gain);
+    }
+}
+
+fn normalize_loudness(buf: &mut [f64], sample_rate: u32) {
+    let frame_len = frame_len(sample_rate);
+    let loud = loud_frames(buf, sample_rate);
+
+    let speech = buf
+        .chunks(frame_len)
+        .zip(&loud)
+        .filter(|(_, loud)| **loud)
+        .flat_map(|(frame, _)| frame.iter().cloned())
+        .collect::<Vec<_>>();
+    let level = rms(&speech);
+    let peak = buf.iter().map(|s| s.abs()).fold(0.0, f64::max);
+    if level <= 0.0 || peak <= 0.0 {
+        return;
+    }
+
+    let gain = (from_db(LOUDNESS_TARGET) / level).min(1.0 / peak);
+    buf.iter_mut().for_each(|s| *s *= gain);
+}
+
+/// Spectral subtraction. The noise spectrum is the average of the quietest
+/// frames, which works as long as the audio has some pauses. Audio shorter than
+/// one frame is returned as is
+fn denoise(buf: &[f64], sample_rate: u32) -> Vec<f64> {
+    let fft_len = ((sample_rate as f64 * FFT_SECS) as usize).next_power_of_two();
+    let hop = fft_len / 2;
+    // A periodic Hann window at half overlap adds up to one, so the frames can
+    // be added back together without a synthesis window. Half a frame of
+    // silence on each side lets every sample be covered twice
+    let window = (0..fft_len)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / fft_len as f64).cos())
+        .collect::<Vec<_>>();
+    let mut padded = vec![0.0; hop];
+    padded.extend_from_slice(buf);
+    padded.resize(padded.len() + fft_len, 0.0);
+
+    let frame_starts = (0..=padded.len() - fft_len)
+        .step_by(hop)
+        .collect::<Vec<_>>();
+
+    let spectrum = |start: usize| {
+        let mut re = padded[start..start + fft_len]
+            .iter()
+            .zip(&window)
+            .map(|(s, w)| s * w)
+            .collect::<Vec<_>>();
+        let mut im = vec![0.0; fft_len];
+        fft(&mut re, &mut im, false);
+        (re, im)
+    };
+
+    // Frames reaching into the padding would be quieter than any real noise
+    let mut by_level = frame_starts
+        .iter()
+        .filter(|&&start| start >= hop && start + fft_len <= hop + buf.len())
+        .map(|&start| (rms(&padded[start..start + fft_len]), start))
+        .collect::<Vec<_>>();
+    if by_level.is_empty() {
+        return buf.to_vec();
+    }
+    by_level.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let num_noise_frames = ((by_level.len() as f64 * NOISE_FRACTION) as usize).max(1);
+
+    let mut noise = vec![0.0; fft_len];
+    for &(_, start) in &by_level[..num_noise_frames] {
+        let (re, im) = spectrum(start);
+        for (bin, n) in noise.iter_mut().enumerate() {
+            *n += re[bin].hypot(im[bin]) / num_noise_frames as f64;
+        }
+    }
+
+    let mut out = vec![0.0; padded.len()];
+    for &start in &frame_starts {
+        let (mut re, mut im) = spectrum(start);
+        for bin in 0..fft_len {
+            let magnitude = re[bin].hypot(im[bin]);
+            let gain = if magnitude > 0.0 {
+                (1.0 - OVER_SUBTRACTION * noise[bin] / magnitude).max(SPECTRAL_FLOOR)
+            } else {
+                SPECTRAL_FLOOR
+            };
+            re[bin] *= gain;
+            im[bin] *= gain;
+        }
+
+        fft(&mut re, &mut im, true);
+        for (i, s) in re.iter().enumerate() {
+            out[start + i] += s / fft_len as f64;
+        }
+    }
+
+    out[hop..hop + buf.len()].to_vec()
+}
+
+/// In place radix 2 FFT. The length must be a power of two. The inverse isn't
+/// scaled
+fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        let (step_re, step_im) = (angle.cos(), angle.sin());
+
+        for start in (0..n).step_by(len) {
+            let (mut w_re, mut w_im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+
+                let next_re = w_re * step_re - w_im * step_im;
+                w_im = w_re * step_im + w_im * step_re;
+                w_re = next_re;
+            }
+        }
+
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn sine(freq: f64, amplitude: f64, secs: f64) -> Vec<f64> {
+        (0..(secs * RATE as f64) as usize)
+            .map(|i| amplitude * (2.0 * PI * freq * i as f64 / RATE as f64).sin())
+            .collect()
+    }
+
+    fn silence(secs: f64) -> Vec<f64> {
+        vec![0.0; (secs * RATE as f64) as usize]
+    }
+
+    fn peak(buf: &[f64]) -> f64 {
+        buf.iter().map(|s| s.abs()).fold(0.0, f64::max)
+    }
+
+    /// Deterministic white noise between -amplitude and amplitude
+    fn noise(amplitude: f64, secs: f64) -> Vec<f64> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..(secs * RATE as f64) as usize)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                amplitude * ((state >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trim_keeps_margin() {
+        let tone = sine(440.0, 0.5, 0.5);
+        let mut buf = silence(1.0);
+        buf.extend(&tone);
+        buf.extend(silence(1.0));
+
+        let trimmed = trim(&buf, RATE);
+        let margin = (TRIM_MARGIN_SECS * RATE as f64) as usize;
+        assert!(trimmed.len() >= tone.len() + 2 * margin);
+        assert!(trimmed.len() <= tone.len() + 2 * margin + 2 * frame_len(RATE));
+        // Silence is left on both sides of the tone
+        assert_eq!(peak(&trimmed[..margin / 2]), 0.0);
+        assert_eq!(peak(&trimmed[trimmed.len() - margin / 2..]), 0.0);
+    }
+
+    #[test]
+    fn trim_keeps_silent_input() {
+        let buf = silence(1.0);
+        assert_eq!(trim(&buf, RATE).len(), buf.len());
+    }
+
+    #[test]
+    fn peak_normalization_hits_target() {
+        let mut buf = sine(440.0, 0.1, 0.5);
+        normalize_peak(&mut buf);
+        assert!((to_db(peak(&buf)) - PEAK_TARGET).abs() < 0.01);
+    }
+
+    #[test]
+    fn loudness_normalization_hits_target() {
+        let mut buf = silence(0.5);
+        buf.extend(sine(440.0, 0.01, 1.0));
+        buf.extend(silence(0.5));
+        normalize_loudness(&mut buf, RATE);
+
+        let level = to_db(rms(trim(&buf, RATE)));
+        assert!((level - LOUDNESS_TARGET).abs() < 0.5, "level {}", level);
+    }
+
+    #[test]
+    fn loudness_normalization_does_not_clip() {
+        // A single loud click limits the gain of otherwise quiet audio
+        let mut buf = sine(440.0, 0.001, 1.0);
+        buf[100] = 0.5;
+        normalize_loudness(&mut buf, RATE);
+        assert!((peak(&buf) - 1.0).abs() < 1e-9);
+        assert!(to_db(rms(&buf)) < LOUDNESS_TARGET);
+    }
+
+    #[test]
+    fn remove_dc_removes_offset() {
+        let mut buf = sine(440.0, 0.1, 1.0)
+            .into_iter()
+            .map(|s| s + 0.3)
+            .collect::<Vec<_>>();
+        remove_dc(&mut buf, RATE);
+
+        // Once the filter has settled
+        let settled = &buf[RATE as usize / 2..];
+        let mean = settled.iter().sum::<f64>() / settled.len() as f64;
+        assert!(mean.abs() < 0.005, "mean {}", mean);
+    }
+
+    #[test]
+    fn denoise_reduces_noise_of_short_audio() {
+        // Short enough that the padded frames at the ends would be a large
+        // share of the quietest frames
+        let mut buf = noise(0.01, 0.3);
+        let tone = sine(440.0, 0.3, 0.6);
+        buf.extend(noise(0.01, 0.6).iter().zip(&tone).map(|(n, t)| n + t));
+        buf.extend(noise(0.01, 0.3));
+
+        let denoised = denoise(&buf, RATE);
+        assert_eq!(denoised.len(), buf.len());
+        let quiet = RATE as usize / 20..RATE as usize / 4;
+        let reduction = rms(&denoised[quiet.clone()]) / rms(&buf[quiet]);
+        assert!(reduction < 0.16, "reduced to {}", reduction);
+    }
+
+    #[test]
+    fn denoise_keeps_audio_shorter_than_a_frame() {
+        let buf = noise(0.01, 0.01);
+        assert_eq!(denoise(&buf, RATE), buf);
+    }
+
+    #[test]
+    fn preprocessing_round_trips() {
+        for spec in [
+            "none",
+            "dc",
+            "trim,peak",
+            "dc,denoise,trim,pad=0.2,loudness",
+        ] {
+            let preprocessing = spec.parse::<Preprocessing>().unwrap();
+            assert_eq!(preprocessing.to_string(), spec);
+            assert_eq!(
+                preprocessing.to_string().parse::<Preprocessing>().unwrap(),
+                preprocessing
+            );
+        }
+
+        assert!("pad=-1".parse::<Preprocessing>().is_err());
+        assert!("louder".parse::<Preprocessing>().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use deepspeech::{errors::DeepspeechError, Metadata, Model as DsModel};
+use serde::Serialize;
+
+/// Number of candidate transcripts requested from DeepSpeech
+pub(crate) const NUM_CANDIDATES: u32 = 5;
+
+/// A word that starts within this many seconds of a word with the same text in
+/// another candidate is considered the same word
+const WORD_TIME_TOLERANCE_SECS: f32 = 0.2;
+
+/// Character level token as reported by DeepSpeech
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct Token {
+    pub(crate) text: String,
+    pub(crate) timestep: u32,
+    pub(crate) start_time: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct Word {
+    pub(crate) text: String,
+    pub(crate) start_time: f32,
+    /// Share of the candidates (weighted by probability) that agree on this
+    /// word at this time
+    pub(crate) confidence: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct Candidate {
+    pub(crate) text: String,
+    /// Raw DeepSpeech confidence, the sum of the token log probabilities
+    pub(crate) confidence: f64,
+    /// Probability of this candidate relative to the other candidates
+    pub(crate) probability: f32,
+    pub(crate) tokens: Vec<Token>,
+    pub(crate) words: Vec<Word>,
+}
+
+/// Candidate transcripts for one piece of audio, best first
+#[derive(Serialize, Clone, Debug, Default)]
+pub(crate) struct Recognition {
+    pub(crate) candidates: Vec<Candidate>,
+}
+
+impl Recognition {
+    pub(crate) fn from_metadata(metadata: &Metadata) -> Recognition {
+        let transcripts = metadata.transcripts();
+
+        let max_confidence = transcripts
+            .iter()
+            .map(|t| t.confidence())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights = transcripts
+            .iter()
+            .map(|t| (t.confidence() - max_confidence).exp())
+            .collect::<Vec<_>>();
+        let total_weight = weights.iter().sum::<f64>();
+
+        let mut candidates = transcripts
+            .iter()
+            .zip(&weights)
+            .map(|(transcript, weight)| {
+                let tokens = transcript
+                    .tokens()
+                    .iter()
+                    .map(|t| Token {
+                        text: t.text().to_string(),
+                        timestep: t.timestep(),
+                        start_time: t.start_time(),
+                    })
+                    .collect::<Vec<_>>();
+
+                Candidate {
+                    text: tokens.iter().map(|t| t.text.as_str()).collect(),
+                    confidence: transcript.confidence(),
+                    probability: (weight / total_weight) as f32,
+                    words: split_words(&tokens),
+                    tokens,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for i in 0..candidates.len() {
+            for w in 0..candidates[i].words.len() {
+                let word = &candidates[i].words[w];
+                let confidence = candidates
+                    .iter()
+                    .filter(|c| c.words.iter().any(|other| same_word(word, other)))
+                    .map(|c| c.probability)
+                    .sum();
+                candidates[i].words[w].confidence = confidence;
+            }
+        }
+
+        Recognition { candidates }
+    }
+
+    pub(crate) fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    /// Text of the best candidate, empty if nothing was recognized
+    pub(crate) fn text(&self) -> &str {
+        self.best().map(|c| c.text.as_str()).unwrap_or_default()
+    }
+
+    /// What each candidate heard in place of a word of the best candidate,
+    /// with the total probability of the candidates that heard it, most likely
+    /// first. A candidate that heard nothing there contributes an empty string
+    pub(crate) fn alternatives(&self, word: usize) -> Vec<(String, f32)> {
+        let best_words = match self.best() {
+            Some(best) if word < best.words.len() => &best.words,
+            _ => return Vec::new(),
+        };
+
+        // The word covers the time until the next word of the best candidate
+        let start = best_words[word].start_time - WORD_TIME_TOLERANCE_SECS;
+        let end = best_words
+            .get(word + 1)
+            .map_or(f32::INFINITY, |w| w.start_time - WORD_TIME_TOLERANCE_SECS);
+
+        let mut alternatives: Vec<(String, f32)> = Vec::new();
+        for candidate in &self.candidates {
+            let text = candidate
+                .words
+                .iter()
+                .filter(|w| w.start_time >= start && w.start_time < end)
+                .map(|w| w.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            match alternatives.iter_mut().find(|(t, _)| *t == text) {
+                Some((_, probability)) => *probability += candidate.probability,
+                None => alternatives.push((text, candidate.probability)),
+            }
+        }
+
+        // Bad metadata can hold NaN probabilities, those are sorted last
+        let key = |probability: f32| {
+            if probability.is_nan() {
+                f32::NEG_INFINITY
+            } else {
+                probability
+            }
+        };
+        alternatives.sort_by(|a, b| key(b.1).partial_cmp(&key(a.1)).unwrap());
+        alternatives
+    }
+}
+
+/// Recognizes a complete buffer, keeping up to NUM_CANDIDATES transcripts
+pub(crate) fn recognize(model: &mut DsModel, buf: &[i16]) -> Result<Recognition, DeepspeechError> {
+    let metadata = model.speech_to_text_with_metadata(buf, NUM_CANDIDATES)?;
+    Ok(Recognition::from_metadata(&metadata))
+}
+
+fn split_words(tokens: &[Token]) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+
+    for token in tokens {
+        if token.text.trim().is_empty() {
+            words.extend(current.take());
+            continue;
+        }
+
+        match &mut current {
+            Some(word) => word.text.push_str(&token.text),
+            None => {
+                current = Some(Word {
+                    text: token.text.clone(),
+                    start_time: token.start_time,
+                    confidence: 1.0,
+                })
+            }
+        }
+    }
+
+    words.extend(current);
+    words
+}
+
+fn same_word(a: &Word, b: &Word) -> bool {
+    a.text == b.text && (a.start_time - b.start_time).abs() <= WORD_TIME_TOLERANCE_SECS
+}
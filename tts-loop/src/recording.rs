@@ -0,0 +1,377 @@
+use crate::{
+    audio::{self, AudioManager},
+    resample::ResampleQuality,
+    stt_stream::SttStream,
+    vad::{self, Vad, VadConfig},
+};
+
+use deepspeech::{errors::DeepspeechError, Model as DsModel};
+use log::warn;
+use thiserror::Error as ThisError;
+
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+/// How often reopening a lost input device is attempted while recording
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long stopping waits for the input stream to hand over its last audio
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Deepspeech(#[from] DeepspeechError),
+    #[error(transparent)]
+    Audio(#[from] audio::Error),
+    #[error("Recording interrupted: {0}. It resumes when the input device is available again")]
+    Interrupted(audio::Error),
+    #[error("Not recording")]
+    NotRecording,
+}
+
+/// Recorded audio, or the error that ended the input stream
+type Event = Result<Vec<i16>, audio::Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RecordingState {
+    Idle,
+    /// Voice activated, waiting for speech
+    Listening,
+    Recording,
+    Paused,
+}
+
+impl RecordingState {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            RecordingState::Idle => "idle",
+            RecordingState::Listening => "listening",
+            RecordingState::Recording => "recording",
+            RecordingState::Paused => "paused",
+        }
+    }
+}
+
+pub(crate) struct Ongoing {
+    /// None while the input device is unavailable
+    stream: Option<audio::Stream>,
+    /// None once stopping, so that the channel closes when the stream is gone
+    tx: Option<mpsc::Sender<Event>>,
+    rx: mpsc::Receiver<Event>,
+    /// Recorded at the model's rate
+    buf: Vec<i16>,
+    sample_rate: u32,
+    stt_stream: SttStream,
+    last_reconnect: Instant,
+    /// Audio arriving while paused is dropped
+    paused: bool,
+    /// Samples after which recording stops by itself
+    max_len: Option<usize>,
+    /// Set if recording is voice activated
+    vad: Option<Vad>,
+    /// Latest audio while waiting for speech, added to the recording once it
+    /// starts. None once speech started or if not voice activated
+    pre_roll: Option<VecDeque<i16>>,
+    pre_roll_len: usize,
+    /// Set once voice activated recording heard the end of speech. Audio after
+    /// it is dropped
+    speech_ended: bool,
+}
+
+impl Ongoing {
+    /// Adds recorded to the recording, up to max_len. Returns the latest
+    /// intermediate transcript, if a new one was decoded
+    fn append(&mut self, mut recorded: Vec<i16>) -> Result<Option<String>, DeepspeechError> {
+        if self.paused || self.speech_ended {
+            return Ok(None);
+        }
+
+        if let Some(vad) = &mut self.vad {
+            let speaking = vad.process(&recorded);
+            match &mut self.pre_roll {
+                Some(pre_roll) => {
+                    pre_roll.extend(recorded);
+                    let excess = pre_roll.len().saturating_sub(self.pre_roll_len);
+                    pre_roll.drain(..excess);
+                    if !speaking {
+                        return Ok(None);
+                    }
+                    recorded = pre_roll.drain(..).collect();
+                    self.pre_roll = None;
+                }
+                // The hang-over is kept, so that the end of speech isn't cut off
+                None => self.speech_ended = !speaking,
+            }
+        }
+
+        if let Some(max_len) = self.max_len {
+            recorded.truncate(max_len.saturating_sub(self.buf.len()));
+        }
+        if recorded.is_empty() {
+            return Ok(None);
+        }
+
+        let text = self.stt_stream.feed(&recorded, self.sample_rate)?;
+        self.buf.extend(recorded);
+        Ok(text)
+    }
+
+    /// Closes the input stream and adds everything it recorded before closing
+    fn flush(&mut self) -> Result<(), DeepspeechError> {
+        // The channel closes once the stream and its callbacks are dropped, by
+        // which point they have sent all they held back
+        self.stream = None;
+        self.tx = None;
+
+        loop {
+            match self.rx.recv_timeout(STOP_TIMEOUT) {
+                Ok(Ok(recorded)) => {
+                    self.append(recorded)?;
+                }
+                // Nothing is lost if the stream fails while closing
+                Ok(Err(_)) => (),
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) => {
+                    warn!("Input stream did not close, the end of the recording may be missing");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+pub(crate) enum Recording {
+    Ongoing(Ongoing),
+    Idle,
+}
+
+/// What a recording captured once stopped
+pub(crate) struct Stopped {
+    pub(crate) audio: Vec<i16>,
+    pub(crate) sample_rate: u32,
+    /// Recognition fed while recording. None if feeding it failed
+    pub(crate) stt_stream: Option<SttStream>,
+}
+
+impl Recording {
+    /// Starts recording at the model's rate. Stops by itself after
+    /// max_duration
+    ///
+    /// With a voice activation config only speech is recorded. Nothing is kept
+    /// until speech starts, and [`Recording::speech_ended`] tells when it is
+    /// over
+    pub(crate) fn start(
+        &mut self,
+        audio_manager: &AudioManager,
+        stt_model: &mut DsModel,
+        resample_quality: ResampleQuality,
+        max_duration: Option<Duration>,
+        voice_activation: Option<&VadConfig>,
+    ) -> Result<(), Error> {
+        if let Recording::Ongoing(_) = self {
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let sample_rate = stt_model.get_sample_rate() as u32;
+        let stt_stream = SttStream::new(stt_model, resample_quality)?;
+
+        let stream = record(audio_manager, sample_rate, &tx)?;
+
+        *self = Recording::Ongoing(Ongoing {
+            stream: Some(stream),
+            tx: Some(tx),
+            rx,
+            buf: Vec::new(),
+            sample_rate,
+            stt_stream,
+            last_reconnect: Instant::now(),
+            paused: false,
+            max_len: max_duration.map(|d| (d.as_secs_f64() * sample_rate as f64) as usize),
+            vad: voice_activation.map(|config| Vad::new(config, sample_rate)),
+            pre_roll: voice_activation.map(|_| VecDeque::new()),
+            pre_roll_len: (vad::PRE_ROLL_SECS * sample_rate as f64) as usize,
+            speech_ended: false,
+        });
+
+        Ok(())
+    }
+
+    pub(crate) fn is_ongoing(&self) -> bool {
+        match self {
+            Recording::Ongoing(_) => true,
+            Recording::Idle => false,
+        }
+    }
+
+    pub(crate) fn state(&self) -> RecordingState {
+        match self {
+            Recording::Ongoing(ongoing) if ongoing.paused => RecordingState::Paused,
+            Recording::Ongoing(ongoing) if ongoing.pre_roll.is_some() => RecordingState::Listening,
+            Recording::Ongoing(_) => RecordingState::Recording,
+            Recording::Idle => RecordingState::Idle,
+        }
+    }
+
+    /// Length of the ongoing recording, not counting pauses
+    pub(crate) fn elapsed(&self) -> Duration {
+        match self {
+            Recording::Ongoing(ongoing) => {
+                Duration::from_secs_f64(ongoing.buf.len() as f64 / ongoing.sample_rate as f64)
+            }
+            Recording::Idle => Duration::ZERO,
+        }
+    }
+
+    /// Whether the ongoing recording is as long as it is allowed to get
+    pub(crate) fn reached_limit(&self) -> bool {
+        match self {
+            Recording::Ongoing(ongoing) => ongoing
+                .max_len
+                .map_or(false, |max_len| ongoing.buf.len() >= max_len),
+            Recording::Idle => false,
+        }
+    }
+
+    /// Whether a voice activated recording heard speech end
+    pub(crate) fn speech_ended(&self) -> bool {
+        match self {
+            Recording::Ongoing(ongoing) => ongoing.speech_ended,
+            Recording::Idle => false,
+        }
+    }
+
+    /// Feeds audio recorded since the last call into recognition. Returns the
+    /// latest intermediate transcript, if a new one was decoded
+    ///
+    /// If the input stream failed it is closed and an error returned. What was
+    /// recorded so far is kept and [`Recording::reconnect`] resumes recording
+    pub(crate) fn process(&mut self) -> Result<Option<String>, Error> {
+        let ongoing = match self {
+            Recording::Ongoing(ongoing) => ongoing,
+            Recording::Idle => return Ok(None),
+        };
+
+        let mut latest_text = None;
+        let mut stream_err = None;
+
+        while let Ok(event) = ongoing.rx.try_recv() {
+            match event {
+                Ok(recorded) => {
+                    if let Some(text) = ongoing.append(recorded)? {
+                        latest_text = Some(text);
+                    }
+                }
+                Err(err) => {
+                    // Only the first error of a stream is reported
+                    if ongoing.stream.take().is_some() {
+                        ongoing.last_reconnect = Instant::now();
+                        stream_err = Some(err);
+                    }
+                }
+            }
+        }
+
+        match stream_err {
+            Some(err) => Err(Error::Interrupted(err)),
+            None => Ok(latest_text),
+        }
+    }
+
+    /// Reopens the input stream if it failed, at most every RECONNECT_INTERVAL.
+    /// Returns true if recording resumed
+    pub(crate) fn reconnect(&mut self, audio_manager: &AudioManager) -> bool {
+        let ongoing = match self {
+            Recording::Ongoing(ongoing) => ongoing,
+            Recording::Idle => return false,
+        };
+
+        let tx = match &ongoing.tx {
+            Some(tx) if ongoing.stream.is_none() => tx,
+            _ => return false,
+        };
+        if ongoing.last_reconnect.elapsed() < RECONNECT_INTERVAL {
+            return false;
+        }
+        ongoing.last_reconnect = Instant::now();
+
+        match record(audio_manager, ongoing.sample_rate, tx) {
+            Ok(stream) => {
+                ongoing.stream = Some(stream);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// While paused recorded audio is dropped. Audio that arrived before the
+    /// change but hasn't been processed is treated as if it came after, so
+    /// call [`Recording::process`] first
+    pub(crate) fn set_paused(&mut self, paused: bool) -> Result<(), Error> {
+        match self {
+            Recording::Ongoing(ongoing) => {
+                ongoing.paused = paused;
+                Ok(())
+            }
+            Recording::Idle => Err(Error::NotRecording),
+        }
+    }
+
+    /// Stops recording without waiting for the input stream, dropping
+    /// everything recorded
+    pub(crate) fn discard(&mut self) {
+        if self.is_ongoing() {
+            *self = Recording::Idle;
+        }
+    }
+
+    /// Stops recording, returning everything that was recorded. Waits for the
+    /// input stream to deliver all of its audio first
+    pub(crate) fn stop(&mut self) -> Result<Stopped, Error> {
+        let mut ongoing = match std::mem::replace(self, Recording::Idle) {
+            Recording::Ongoing(ongoing) => ongoing,
+            Recording::Idle => return Err(Error::NotRecording),
+        };
+
+        // The audio is kept even if recognition failed, it can be transcribed
+        // again from the take
+        let stt_stream = match ongoing.flush() {
+            Ok(()) => Some(ongoing.stt_stream),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        };
+
+        Ok(Stopped {
+            audio: ongoing.buf,
+            sample_rate: ongoing.sample_rate,
+            stt_stream,
+        })
+    }
+}
+
+/// Opens an input stream at sample_rate that sends what it records, and any
+/// error that ends it, to tx
+fn record(
+    audio_manager: &AudioManager,
+    sample_rate: u32,
+    tx: &mpsc::Sender<Event>,
+) -> Result<audio::Stream, audio::Error> {
+    let data_tx = tx.clone();
+    let error_tx = tx.clone();
+
+    audio_manager.input_stream(
+        sample_rate,
+        move |buf| {
+            let _ = data_tx.send(Ok(buf.to_owned()));
+        },
+        move |err| {
+            let _ = error_tx.send(Err(err));
+        },
+    )
+}
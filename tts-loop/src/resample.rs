@@ -0,0 +1,357 @@
+//! Sample rate conversion with a windowed sinc filter. Filter coefficients are
+//! precomputed for every output phase (polyphase) unless the rates need too
+//! many of them
+
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::PI;
+
+/// Largest polyphase table built, in coefficients. Rate pairs that would need
+/// more compute the filter for every sample instead
+const MAX_TABLE_SIZE: usize = 1 << 20;
+
+/// Trade-off between resampling speed and how much aliasing and high frequency
+/// loss it introduces
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResampleQuality {
+    Fast,
+    Medium,
+    High,
+}
+
+impl Default for ResampleQuality {
+    fn default() -> ResampleQuality {
+        ResampleQuality::Medium
+    }
+}
+
+impl std::str::FromStr for ResampleQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ResampleQuality, String> {
+        match s {
+            "fast" => Ok(ResampleQuality::Fast),
+            "medium" => Ok(ResampleQuality::Medium),
+            "high" => Ok(ResampleQuality::High),
+            _ => Err(format!(
+                "Invalid resample quality \"{}\", expected fast, medium or high",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ResampleQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            ResampleQuality::Fast => "fast",
+            ResampleQuality::Medium => "medium",
+            ResampleQuality::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ResampleQuality {
+    /// Zero crossings of the sinc on each side of the filter. More gives a
+    /// steeper cutoff
+    fn zero_crossings(self) -> usize {
+        match self {
+            ResampleQuality::Fast => 8,
+            ResampleQuality::Medium => 16,
+            ResampleQuality::High => 32,
+        }
+    }
+
+    /// Cutoff relative to the lower Nyquist frequency. The steeper the filter
+    /// the closer to Nyquist it can start without aliasing
+    fn rolloff(self) -> f64 {
+        match self {
+            ResampleQuality::Fast => 0.8,
+            ResampleQuality::Medium => 0.9,
+            ResampleQuality::High => 0.95,
+        }
+    }
+}
+
+/// Windowed sinc low pass filter, evaluated at a distance from its center in
+/// input samples
+struct Filter {
+    /// Cutoff relative to the input Nyquist frequency
+    cutoff: f64,
+    /// Half length in input samples
+    half_width: f64,
+}
+
+impl Filter {
+    fn at(&self, distance: f64) -> f64 {
+        let x = distance / self.half_width;
+        if x.abs() >= 1.0 {
+            return 0.0;
+        }
+
+        // Blackman window
+        let window = 0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos();
+        self.cutoff * sinc(self.cutoff * distance) * window
+    }
+}
+
+/// Converts mono audio from one sample rate to another. Audio can be fed in
+/// chunks of any size, the result is the same as converting it in one go
+pub(crate) struct Resampler {
+    /// Rates divided by their greatest common divisor
+    from_rate: u32,
+    to_rate: u32,
+    filter: Filter,
+    /// Input samples on each side of an output sample that the filter reaches
+    reach: usize,
+    /// Coefficients for the 2 * reach input samples around an output sample,
+    /// for each of the to_rate output phases
+    table: Option<Vec<f32>>,
+    /// Input still in reach of the filter. Starts with zeros so that the first
+    /// output sample is centered on the first input sample
+    input: Vec<f32>,
+    /// Next output sample is at input[pos] + frac / to_rate
+    pos: usize,
+    frac: u32,
+    num_in: u64,
+    num_out: u64,
+}
+
+impl Resampler {
+    pub(crate) fn new(from_rate: u32, to_rate: u32, quality: ResampleQuality) -> Resampler {
+        let divisor = gcd(from_rate, to_rate);
+        let (from_rate, to_rate) = (from_rate / divisor, to_rate / divisor);
+
+        let cutoff = (to_rate as f64 / from_rate as f64).min(1.0) * quality.rolloff();
+        let filter = Filter {
+            cutoff,
+            half_width: quality.zero_crossings() as f64 / cutoff,
+        };
+        let reach = filter.half_width.floor() as usize + 1;
+
+        let taps = 2 * reach;
+        let table = if from_rate != to_rate && taps * to_rate as usize <= MAX_TABLE_SIZE {
+            let table = (0..to_rate)
+                .flat_map(|frac| {
+                    let offset = frac as f64 / to_rate as f64;
+                    let filter = &filter;
+                    (0..taps)
+                        .map(move |tap| filter.at(offset + (reach - 1) as f64 - tap as f64) as f32)
+                })
+                .collect();
+            Some(table)
+        } else {
+            None
+        };
+
+        Resampler {
+            from_rate,
+            to_rate,
+            filter,
+            reach,
+            table,
+            input: vec![0.0; reach],
+            pos: reach,
+            frac: 0,
+            num_in: 0,
+            num_out: 0,
+        }
+    }
+
+    /// Converts buf, returning as much output as can be computed so far. The
+    /// rest is held back until more input arrives or [`Resampler::finish`]
+    pub(crate) fn process(&mut self, buf: &[i16]) -> Vec<i16> {
+        if self.from_rate == self.to_rate {
+            return buf.to_vec();
+        }
+
+        self.num_in += buf.len() as u64;
+        self.input.extend(buf.iter().map(|&sample| sample as f32));
+
+        let mut out = Vec::new();
+        self.run(&mut out);
+        out
+    }
+
+    /// Returns the output held back for the end of the input
+    pub(crate) fn finish(mut self) -> Vec<i16> {
+        if self.from_rate == self.to_rate {
+            return Vec::new();
+        }
+
+        // Silence after the input lets the filter reach the last samples
+        self.input
+            .extend(std::iter::repeat(0.0).take(self.reach + 1));
+
+        let mut out = Vec::new();
+        self.run(&mut out);
+
+        // Output samples past the end of the input only hold the padding
+        let to_rate = self.to_rate as u64;
+        let from_rate = self.from_rate as u64;
+        let total = (self.num_in * to_rate + from_rate - 1) / from_rate;
+        out.truncate((total - (self.num_out - out.len() as u64)) as usize);
+        out
+    }
+
+    fn run(&mut self, out: &mut Vec<i16>) {
+        let start_len = out.len();
+
+        while self.pos + self.reach < self.input.len() {
+            let sample = self.next_sample();
+            out.push(sample.round().max(i16::MIN as f64).min(i16::MAX as f64) as i16);
+
+            self.frac += self.from_rate;
+            self.pos += (self.frac / self.to_rate) as usize;
+            self.frac %= self.to_rate;
+        }
+        self.num_out += (out.len() - start_len) as u64;
+
+        // Drop input the filter can no longer reach
+        let consumed = self.pos.saturating_sub(self.reach);
+        self.input.drain(..consumed);
+        self.pos -= consumed;
+    }
+
+    fn next_sample(&self) -> f64 {
+        let taps = 2 * self.reach;
+        let first = self.pos + 1 - self.reach;
+        let input = &self.input[first..first + taps];
+
+        match &self.table {
+            Some(table) => {
+                let coefficients = &table[self.frac as usize * taps..][..taps];
+                input
+                    .iter()
+                    .zip(coefficients)
+                    .map(|(&sample, &coefficient)| sample as f64 * coefficient as f64)
+                    .sum()
+            }
+            None => {
+                let offset = self.frac as f64 / self.to_rate as f64;
+                input
+                    .iter()
+                    .enumerate()
+                    .map(|(tap, &sample)| {
+                        let distance = offset + (self.reach - 1) as f64 - tap as f64;
+                        sample as f64 * self.filter.at(distance)
+                    })
+                    .sum()
+            }
+        }
+    }
+}
+
+/// Converts all of buf from from_rate to to_rate
+pub(crate) fn resample(
+    buf: &[i16],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<i16> {
+    let mut resampler = Resampler::new(from_rate, to_rate, quality);
+    let mut out = resampler.process(buf);
+    out.extend(resampler.finish());
+    out
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let rem = a % b;
+        a = b;
+        b = rem;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise in [-amplitude, amplitude]
+    fn noise(len: usize, amplitude: f32) -> Vec<i16> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                ((state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunked_matches_one_shot() {
+        let buf = noise(20000, 10000.0);
+        // 44101 Hz doesn't share a divisor with 16 kHz, so it computes the
+        // filter for every sample instead of using a table
+        for &(from_rate, to_rate) in &[(22050, 16000), (16000, 48000), (44101, 16000)] {
+            let expected = resample(&buf, from_rate, to_rate, ResampleQuality::Medium);
+
+            let mut resampler = Resampler::new(from_rate, to_rate, ResampleQuality::Medium);
+            let mut out = Vec::new();
+            for chunk in buf.chunks(777) {
+                out.extend(resampler.process(chunk));
+            }
+            out.extend(resampler.finish());
+
+            assert_eq!(out, expected, "{} -> {}", from_rate, to_rate);
+        }
+    }
+
+    #[test]
+    fn output_length_follows_rates() {
+        let buf = noise(10007, 10000.0);
+        for &(from_rate, to_rate) in &[(22050, 16000), (16000, 44100), (48000, 8000)] {
+            let out = resample(&buf, from_rate, to_rate, ResampleQuality::Fast);
+            let expected = buf.len() as f64 * to_rate as f64 / from_rate as f64;
+            assert!(
+                (out.len() as f64 - expected).abs() <= 1.0,
+                "{} -> {}: {} samples, expected {}",
+                from_rate,
+                to_rate,
+                out.len(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn dc_gain_is_one() {
+        let buf = vec![10000; 16000];
+        for &quality in &[
+            ResampleQuality::Fast,
+            ResampleQuality::Medium,
+            ResampleQuality::High,
+        ] {
+            let out = resample(&buf, 22050, 16000, quality);
+            // The filter rings at both ends, only the middle is steady
+            let middle = &out[out.len() / 4..out.len() * 3 / 4];
+            for &sample in middle {
+                assert!((sample - 10000).abs() <= 10, "{:?}: {}", quality, sample);
+            }
+        }
+    }
+
+    #[test]
+    fn same_rate_passes_through() {
+        let buf = noise(5000, 30000.0);
+        assert_eq!(resample(&buf, 16000, 16000, ResampleQuality::High), buf);
+
+        let mut resampler = Resampler::new(16000, 16000, ResampleQuality::High);
+        assert_eq!(resampler.process(&buf[..1000]), &buf[..1000]);
+        assert_eq!(resampler.process(&buf[1000..]), &buf[1000..]);
+        assert!(resampler.finish().is_empty());
+    }
+}
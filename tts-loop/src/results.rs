@@ -0,0 +1,62 @@
+use crate::{
+    config::DecoderConfig,
+    hot_words::HotWord,
+    model::ModelInfo,
+    recognition::Recognition,
+    scoring::{Scores, ScoringOptions},
+};
+
+use serde::Serialize;
+
+use std::{fs::File, io, path::Path};
+
+#[derive(Serialize)]
+pub(crate) struct Iteration {
+    pub(crate) text: String,
+    pub(crate) scores: Scores,
+    pub(crate) recognition: Recognition,
+}
+
+/// Everything needed to compare one loop run against another, written next to
+/// the saved wav
+#[derive(Serialize, Default)]
+pub(crate) struct RunResults {
+    pub(crate) input: String,
+    pub(crate) voice: String,
+    pub(crate) scoring: ScoringOptions,
+    pub(crate) model: ModelInfo,
+    pub(crate) decoder: DecoderConfig,
+    /// Hot words in effect, none without a scorer
+    pub(crate) hot_words: Vec<HotWord>,
+    /// Name of the recording that is iteration 0, if the loop started from one
+    pub(crate) take: Option<String>,
+    pub(crate) iterations: Vec<Iteration>,
+}
+
+impl RunResults {
+    pub(crate) fn new(
+        input: String,
+        voice: String,
+        scoring: ScoringOptions,
+        model: ModelInfo,
+        decoder: DecoderConfig,
+        hot_words: Vec<HotWord>,
+    ) -> RunResults {
+        RunResults {
+            input,
+            voice,
+            scoring,
+            model,
+            decoder,
+            hot_words,
+            take: None,
+            iterations: Vec::new(),
+        }
+    }
+
+    pub(crate) fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let f = File::create(path)?;
+        serde_json::to_writer_pretty(f, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+use serde::Serialize;
+
+use std::{ffi::NulError, fmt};
+
+/// Edit counts from aligning a hypothesis against a reference sequence
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub(crate) struct ErrorRate {
+    pub(crate) substitutions: usize,
+    pub(crate) deletions: usize,
+    pub(crate) insertions: usize,
+    pub(crate) reference_len: usize,
+}
+
+impl ErrorRate {
+    pub(crate) fn rate(&self) -> f32 {
+        let errors = self.substitutions + self.deletions + self.insertions;
+        if self.reference_len == 0 {
+            return if errors == 0 { 0.0 } else { 1.0 };
+        }
+
+        errors as f32 / self.reference_len as f32
+    }
+}
+
+impl fmt::Display for ErrorRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% (S{} D{} I{})",
+            self.rate() * 100.0,
+            self.substitutions,
+            self.deletions,
+            self.insertions
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EditOp {
+    Match,
+    Substitution,
+    Deletion,
+    Insertion,
+}
+
+/// Minimum edit distance alignment between reference and hypothesis, returned
+/// as the list of operations turning reference into hypothesis
+fn align<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> Vec<EditOp> {
+    let width = hypothesis.len() + 1;
+    let mut costs = vec![0usize; (reference.len() + 1) * width];
+
+    for (i, cost) in costs.iter_mut().step_by(width).enumerate() {
+        *cost = i;
+    }
+
+    for (j, cost) in costs.iter_mut().take(width).enumerate() {
+        *cost = j;
+    }
+
+    for i in 1..=reference.len() {
+        for j in 1..=hypothesis.len() {
+            let sub_cost = if reference[i - 1] == hypothesis[j - 1] {
+                0
+            } else {
+                1
+            };
+
+            costs[i * width + j] = (costs[(i - 1) * width + j - 1] + sub_cost)
+                .min(costs[(i - 1) * width + j] + 1)
+                .min(costs[i * width + j - 1] + 1);
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (reference.len(), hypothesis.len());
+    while i > 0 || j > 0 {
+        let cost = costs[i * width + j];
+        if i > 0 && j > 0 {
+            let is_match = reference[i - 1] == hypothesis[j - 1];
+            let diag_cost = costs[(i - 1) * width + j - 1] + if is_match { 0 } else { 1 };
+            if diag_cost == cost {
+                ops.push(if is_match {
+                    EditOp::Match
+                } else {
+                    EditOp::Substitution
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+
+        if i > 0 && costs[(i - 1) * width + j] + 1 == cost {
+            ops.push(EditOp::Deletion);
+            i -= 1;
+        } else {
+            ops.push(EditOp::Insertion);
+            j -= 1;
+        }
+    }
+
+    ops.reverse();
+    ops
+}
+
+pub(crate) fn error_rate<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> ErrorRate {
+    let mut ret = ErrorRate {
+        reference_len: reference.len(),
+        ..Default::default()
+    };
+
+    for op in align(reference, hypothesis) {
+        match op {
+            EditOp::Match => (),
+            EditOp::Substitution => ret.substitutions += 1,
+            EditOp::Deletion => ret.deletions += 1,
+            EditOp::Insertion => ret.insertions += 1,
+        }
+    }
+
+    ret
+}
+
+/// Splits text into lowercase words the same way DeepSpeech reports them,
+/// dropping any punctuation
+pub(crate) fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Expands numbers, abbreviations and symbols into the words voice would speak
+/// for them
+pub(crate) fn normalize(text: &str, voice: &str) -> Result<String, NulError> {
+    let words = flite::normalize(text, voice.to_string())?
+        .into_iter()
+        .flat_map(|token| token.words.into_iter())
+        .collect::<Vec<_>>();
+
+    Ok(words.join(" "))
+}
+
+/// Phone sequence for words using the voice's lexicon and letter to sound
+/// rules. Stress is dropped so that only the sounds themselves are compared
+pub(crate) fn phonemes(words: &[String], voice: &str) -> Result<Vec<String>, NulError> {
+    let mut ret = Vec::new();
+    for word in words {
+        let phones = flite::word_to_phones(word.as_str(), voice.to_string())?;
+        ret.extend(
+            phones
+                .into_iter()
+                .map(|p| p.trim_end_matches(|c: char| c.is_ascii_digit()).to_string()),
+        );
+    }
+
+    Ok(ret)
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub(crate) struct ScoringOptions {
+    /// Also compute phoneme error rate
+    pub(crate) phoneme: bool,
+    /// Compare the normalized forms of both texts so that "5 km" matches "five
+    /// kilometers"
+    pub(crate) normalize: bool,
+}
+
+/// Reference text prepared once per loop so that every iteration can be scored
+/// against it
+#[derive(Default)]
+pub(crate) struct Reference {
+    options: ScoringOptions,
+    words: Vec<String>,
+    phonemes: Option<Vec<String>>,
+}
+
+impl Reference {
+    pub(crate) fn new(
+        text: &str,
+        options: ScoringOptions,
+        voice: &str,
+    ) -> Result<Reference, NulError> {
+        let words = if options.normalize {
+            words(&normalize(text, voice)?)
+        } else {
+            words(text)
+        };
+
+        let phonemes = if options.phoneme {
+            Some(phonemes(&words, voice)?)
+        } else {
+            None
+        };
+
+        Ok(Reference {
+            options,
+            words,
+            phonemes,
+        })
+    }
+
+    pub(crate) fn score(&self, text: &str, voice: &str) -> Result<Scores, NulError> {
+        let hypothesis_words = if self.options.normalize {
+            words(&normalize(text, voice)?)
+        } else {
+            words(text)
+        };
+
+        let phoneme = match &self.phonemes {
+            Some(reference_phonemes) => {
+                let hypothesis_phonemes = phonemes(&hypothesis_words, voice)?;
+                Some(error_rate(reference_phonemes, &hypothesis_phonemes))
+            }
+            None => None,
+        };
+
+        Ok(Scores {
+            word: error_rate(&self.words, &hypothesis_words),
+            phoneme,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub(crate) struct Scores {
+    pub(crate) word: ErrorRate,
+    pub(crate) phoneme: Option<ErrorRate>,
+}
+
+impl fmt::Display for Scores {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WER: {}", self.word)?;
+        if let Some(phoneme) = &self.phoneme {
+            write!(f, ", PER: {}", phoneme)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn counts(rate: ErrorRate) -> (usize, usize, usize, usize) {
+        (
+            rate.substitutions,
+            rate.deletions,
+            rate.insertions,
+            rate.reference_len,
+        )
+    }
+
+    #[test]
+    fn identical_text_has_no_errors() {
+        let reference = words("The quick brown fox");
+        let rate = error_rate(&reference, &reference);
+        assert_eq!(counts(rate), (0, 0, 0, 4));
+        assert_eq!(rate.rate(), 0.0);
+        assert!(align(&reference, &reference)
+            .iter()
+            .all(|&op| op == EditOp::Match));
+    }
+
+    #[test]
+    fn empty_reference_or_hypothesis() {
+        let empty: Vec<String> = Vec::new();
+        let text = words("hello world");
+
+        let rate = error_rate(&empty, &empty);
+        assert_eq!(counts(rate), (0, 0, 0, 0));
+        assert_eq!(rate.rate(), 0.0);
+
+        let rate = error_rate(&empty, &text);
+        assert_eq!(counts(rate), (0, 0, 2, 0));
+        assert_eq!(rate.rate(), 1.0);
+
+        let rate = error_rate(&text, &empty);
+        assert_eq!(counts(rate), (0, 2, 0, 2));
+        assert_eq!(rate.rate(), 1.0);
+    }
+
+    #[test]
+    fn pure_insertions() {
+        let rate = error_rate(&words("see the cat"), &words("see the big fat cat"));
+        assert_eq!(counts(rate), (0, 0, 2, 3));
+
+        assert_eq!(
+            align(&chars("ac"), &chars("abc")),
+            vec![EditOp::Match, EditOp::Insertion, EditOp::Match]
+        );
+    }
+
+    #[test]
+    fn pure_deletions() {
+        let rate = error_rate(&words("see the big fat cat"), &words("see the cat"));
+        assert_eq!(counts(rate), (0, 2, 0, 5));
+        assert!((rate.rate() - 0.4).abs() < 1e-6);
+
+        assert_eq!(
+            align(&chars("abc"), &chars("ac")),
+            vec![EditOp::Match, EditOp::Deletion, EditOp::Match]
+        );
+    }
+
+    #[test]
+    fn substitutions() {
+        let rate = error_rate(
+            &words("I scream for ice cream"),
+            &words("eye scream four ice cream"),
+        );
+        assert_eq!(counts(rate), (2, 0, 0, 5));
+        assert!((rate.rate() - 0.4).abs() < 1e-6);
+
+        // kitten -> sitting is the textbook edit distance of 3
+        let rate = error_rate(&chars("kitten"), &chars("sitting"));
+        assert_eq!(counts(rate), (2, 0, 1, 6));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    recognition::{Recognition, NUM_CANDIDATES},
+    resample::{ResampleQuality, Resampler},
+};
+
+use deepspeech::{errors::DeepspeechError, Model as DsModel, Stream as DsStream};
+
+/// Amount of audio fed between intermediate decodes, in seconds
+const DECODE_INTERVAL_SECS: f32 = 0.5;
+
+/// DeepSpeech recognition stream that is fed audio while it is still being
+/// produced
+///
+/// Audio is resampled to the model's sample rate as it is fed. It has to keep
+/// the rate of the first chunk, if it changes the stream is marked unusable and
+/// callers should fall back to decoding the complete buffer
+pub(crate) struct SttStream {
+    stream: DsStream,
+    sample_rate: u32,
+    /// Rate of the fed audio and its conversion to sample_rate, set up once
+    /// the first chunk arrives
+    resampler: Option<(u32, Resampler)>,
+    resample_quality: ResampleQuality,
+    samples_since_decode: usize,
+    usable: bool,
+}
+
+impl SttStream {
+    pub(crate) fn new(
+        model: &mut DsModel,
+        resample_quality: ResampleQuality,
+    ) -> Result<SttStream, DeepspeechError> {
+        Ok(SttStream {
+            sample_rate: model.get_sample_rate() as u32,
+            stream: DsStream::from_model(model)?,
+            resampler: None,
+            resample_quality,
+            samples_since_decode: 0,
+            usable: true,
+        })
+    }
+
+    /// Feeds buf into the stream, returning an intermediate transcript every
+    /// DECODE_INTERVAL_SECS of audio
+    pub(crate) fn feed(
+        &mut self,
+        buf: &[i16],
+        sample_rate: u32,
+    ) -> Result<Option<String>, DeepspeechError> {
+        let (model_rate, quality) = (self.sample_rate, self.resample_quality);
+        let (input_rate, resampler) = self.resampler.get_or_insert_with(|| {
+            (
+                sample_rate,
+                Resampler::new(sample_rate, model_rate, quality),
+            )
+        });
+        if *input_rate != sample_rate {
+            self.usable = false;
+        }
+
+        if !self.usable {
+            return Ok(None);
+        }
+
+        let buf = resampler.process(buf);
+        self.stream.feed_audio(&buf);
+        self.samples_since_decode += buf.len();
+
+        let decode_interval = (self.sample_rate as f32 * DECODE_INTERVAL_SECS) as usize;
+        if self.samples_since_decode < decode_interval {
+            return Ok(None);
+        }
+
+        self.samples_since_decode = 0;
+        Ok(Some(self.stream.intermediate_decode()?))
+    }
+
+    pub(crate) fn is_usable(&self) -> bool {
+        self.usable
+    }
+
+    pub(crate) fn finish(mut self) -> Result<Recognition, DeepspeechError> {
+        if let Some((_, resampler)) = self.resampler {
+            self.stream.feed_audio(&resampler.finish());
+        }
+
+        let metadata = self.stream.finish_with_metadata(NUM_CANDIDATES)?;
+        Ok(Recognition::from_metadata(&metadata))
+    }
+}
@@ -0,0 +1,119 @@
+//! Recordings kept for the rest of the session, so they can be played,
+//! transcribed again and fed into the loop
+
+use crate::recognition::Recognition;
+
+use hound::{WavSpec, WavWriter};
+use thiserror::Error as ThisError;
+
+use std::{path::PathBuf, time::Duration};
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Take {0} not found")]
+    NotFound(u32),
+    #[error("Take name cannot be empty")]
+    EmptyName,
+    #[error("Failed to export take to {path}: {err}")]
+    Export { path: PathBuf, err: hound::Error },
+}
+
+pub(crate) struct Take {
+    /// Stays the same when other takes are removed
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) audio: Vec<i16>,
+    pub(crate) sample_rate: u32,
+    /// Latest transcript, None until one is made
+    pub(crate) recognition: Option<Recognition>,
+}
+
+impl Take {
+    pub(crate) fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.audio.len() as f64 / self.sample_rate as f64)
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        self.recognition.as_ref().map_or("", |r| r.text())
+    }
+
+    pub(crate) fn export(&self, path: PathBuf) -> Result<(), Error> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let write = || -> Result<(), hound::Error> {
+            let mut writer = WavWriter::create(&path, spec)?;
+            for sample in &self.audio {
+                writer.write_sample(*sample)?;
+            }
+            writer.finalize()
+        };
+
+        write().map_err(|err| Error::Export { path, err })
+    }
+}
+
+/// Takes in the order they were recorded
+#[derive(Default)]
+pub(crate) struct Takes {
+    takes: Vec<Take>,
+    next_id: u32,
+}
+
+impl Takes {
+    pub(crate) fn entries(&self) -> &[Take] {
+        &self.takes
+    }
+
+    /// Adds a take named after its position in the session. Returns its id
+    pub(crate) fn add(&mut self, audio: Vec<i16>, sample_rate: u32) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.takes.push(Take {
+            id,
+            name: format!("Take {}", id + 1),
+            audio,
+            sample_rate,
+            recognition: None,
+        });
+        id
+    }
+
+    pub(crate) fn get(&self, id: u32) -> Result<&Take, Error> {
+        self.takes
+            .iter()
+            .find(|t| t.id == id)
+            .ok_or(Error::NotFound(id))
+    }
+
+    pub(crate) fn get_mut(&mut self, id: u32) -> Result<&mut Take, Error> {
+        self.takes
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or(Error::NotFound(id))
+    }
+
+    pub(crate) fn rename(&mut self, id: u32, name: &str) -> Result<(), Error> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::EmptyName);
+        }
+
+        self.get_mut(id)?.name = name.to_string();
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, id: u32) -> Result<Take, Error> {
+        let idx = self
+            .takes
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or(Error::NotFound(id))?;
+        Ok(self.takes.remove(idx))
+    }
+}
@@ -0,0 +1,302 @@
+//! Energy based voice activity detection, used to start and stop recordings
+//! hands-free. The level of each frame is compared to a tracked noise floor,
+//! so the detector adapts to the room and the microphone gain
+
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+/// Frames the level is measured over
+const FRAME_SECS: f64 = 0.02;
+
+/// Speech has to last this long before it counts, so that clicks and knocks
+/// don't start a recording
+const ONSET_SECS: f64 = 0.1;
+
+/// Audio kept from before speech was detected, so that the onset and the first
+/// syllable aren't cut off
+pub(crate) const PRE_ROLL_SECS: f64 = 0.3;
+
+/// Frames quieter than this are never speech, in dBFS
+const MIN_SPEECH_LEVEL: f32 = -60.0;
+
+/// Level of digital silence, in dBFS
+const SILENCE_LEVEL: f32 = -100.0;
+
+/// Range of the level above the noise floor that counts as speech, in dB. The
+/// highest sensitivity uses the lowest threshold
+const MIN_THRESHOLD: f32 = 3.0;
+const MAX_THRESHOLD: f32 = 20.0;
+
+/// How fast the noise floor follows the level of frames without speech. It
+/// drops quickly and rises slowly, so short pauses in speech don't raise it
+const FLOOR_FALL_RATE: f32 = 0.5;
+const FLOOR_RISE_RATE: f32 = 0.05;
+/// How fast the noise floor follows frames with speech, so that a noise that
+/// starts and doesn't stop eventually stops counting as speech
+const FLOOR_SPEECH_RATE: f32 = 0.002;
+
+const DEFAULT_SENSITIVITY: f32 = 0.5;
+const DEFAULT_HANGOVER_SECS: f32 = 1.0;
+
+/// Voice activated recording settings. Unset values use the defaults
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VadConfig {
+    /// Listen for speech whenever nothing else is running, record it and
+    /// transcribe it once it ends
+    pub enabled: Option<bool>,
+    /// 0 to 1. Higher values pick up quieter speech, but also more noise
+    pub sensitivity: Option<f32>,
+    /// Seconds of silence after which speech is considered over
+    pub hangover_secs: Option<f32>,
+    /// Iterations of the loop run on each transcript, 0 to only transcribe
+    pub loop_iters: Option<u32>,
+}
+
+impl VadConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub(crate) fn sensitivity(&self) -> f32 {
+        match self.sensitivity {
+            Some(sensitivity) if sensitivity.is_finite() => sensitivity.clamp(0.0, 1.0),
+            _ => DEFAULT_SENSITIVITY,
+        }
+    }
+
+    pub(crate) fn hangover(&self) -> Duration {
+        match self.hangover_secs {
+            Some(secs) if secs.is_finite() && secs >= 0.0 => Duration::from_secs_f32(secs),
+            _ => Duration::from_secs_f32(DEFAULT_HANGOVER_SECS),
+        }
+    }
+
+    pub(crate) fn loop_iters(&self) -> u32 {
+        self.loop_iters.unwrap_or(0)
+    }
+
+    /// Copy of self with every value filled in
+    pub(crate) fn effective(&self) -> VadConfig {
+        VadConfig {
+            enabled: Some(self.enabled()),
+            sensitivity: Some(self.sensitivity()),
+            hangover_secs: Some(self.hangover().as_secs_f32()),
+            loop_iters: Some(self.loop_iters()),
+        }
+    }
+
+    /// Fills in every value not set in self from other
+    pub(crate) fn or(self, other: VadConfig) -> VadConfig {
+        VadConfig {
+            enabled: self.enabled.or(other.enabled),
+            sensitivity: self.sensitivity.or(other.sensitivity),
+            hangover_secs: self.hangover_secs.or(other.hangover_secs),
+            loop_iters: self.loop_iters.or(other.loop_iters),
+        }
+    }
+}
+
+/// Tells speech apart from background noise in a stream of mono audio
+pub(crate) struct Vad {
+    frame_len: usize,
+    /// Samples of the frame being collected
+    frame: Vec<i16>,
+    /// Level above noise_floor that counts as speech, in dB
+    threshold: f32,
+    /// None until the first frame is measured
+    noise_floor: Option<f32>,
+    onset_frames: usize,
+    hangover_frames: usize,
+    /// Consecutive frames above or below the threshold
+    loud_frames: usize,
+    quiet_frames: usize,
+    speaking: bool,
+}
+
+impl Vad {
+    pub(crate) fn new(config: &VadConfig, sample_rate: u32) -> Vad {
+        let frame_len = ((sample_rate as f64 * FRAME_SECS) as usize).max(1);
+        let frames = |secs: f64| (secs / FRAME_SECS).ceil() as usize;
+
+        Vad {
+            frame_len,
+            frame: Vec::with_capacity(frame_len),
+            threshold: MAX_THRESHOLD - config.sensitivity() * (MAX_THRESHOLD - MIN_THRESHOLD),
+            noise_floor: None,
+            onset_frames: frames(ONSET_SECS).max(1),
+            hangover_frames: frames(config.hangover().as_secs_f64()).max(1),
+            loud_frames: 0,
+            quiet_frames: 0,
+            speaking: false,
+        }
+    }
+
+    /// Feeds recorded audio. Returns whether speech is ongoing at its end
+    pub(crate) fn process(&mut self, samples: &[i16]) -> bool {
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() == self.frame_len {
+                self.process_frame();
+                self.frame.clear();
+            }
+        }
+
+        self.speaking
+    }
+
+    fn process_frame(&mut self) {
+        let level = level(&self.frame);
+        let noise_floor = *self.noise_floor.get_or_insert(level);
+        let loud = level >= MIN_SPEECH_LEVEL && level - noise_floor >= self.threshold;
+
+        if loud {
+            self.loud_frames += 1;
+            self.quiet_frames = 0;
+        } else {
+            self.quiet_frames += 1;
+            self.loud_frames = 0;
+        }
+
+        if !self.speaking && self.loud_frames >= self.onset_frames {
+            self.speaking = true;
+        } else if self.speaking && self.quiet_frames >= self.hangover_frames {
+            self.speaking = false;
+        }
+
+        let rate = if level < noise_floor {
+            FLOOR_FALL_RATE
+        } else if loud {
+            FLOOR_SPEECH_RATE
+        } else {
+            FLOOR_RISE_RATE
+        };
+        self.noise_floor = Some(noise_floor + (level - noise_floor) * rate);
+    }
+}
+
+/// RMS level of samples in dBFS
+fn level(samples: &[i16]) -> f32 {
+    let power = samples
+        .iter()
+        .map(|&s| (s as f64 / i16::MAX as f64).powi(2))
+        .sum::<f64>()
+        / samples.len().max(1) as f64;
+
+    if power > 0.0 {
+        ((10.0 * power.log10()) as f32).max(SILENCE_LEVEL)
+    } else {
+        SILENCE_LEVEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn frame_len() -> usize {
+        (SAMPLE_RATE as f64 * FRAME_SECS) as usize
+    }
+
+    fn frames(secs: f64) -> usize {
+        (secs / FRAME_SECS).ceil() as usize
+    }
+
+    /// One frame of deterministic background noise, around -55 dBFS
+    fn quiet_frame(state: &mut u32) -> Vec<i16> {
+        (0..frame_len())
+            .map(|_| {
+                *state ^= *state << 13;
+                *state ^= *state >> 17;
+                *state ^= *state << 5;
+                (*state % 201) as i16 - 100
+            })
+            .collect()
+    }
+
+    /// One frame of a 440 Hz tone, around -13 dBFS
+    fn loud_frame() -> Vec<i16> {
+        (0..frame_len())
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 10000.0) as i16
+            })
+            .collect()
+    }
+
+    /// Vad that has settled on a second of background noise
+    fn settled_vad(config: &VadConfig, state: &mut u32) -> Vad {
+        let mut vad = Vad::new(config, SAMPLE_RATE);
+        for _ in 0..frames(1.0) {
+            assert!(!vad.process(&quiet_frame(state)));
+        }
+        vad
+    }
+
+    #[test]
+    fn speech_starts_after_onset() {
+        let mut state = 0x2545_f491;
+        let mut vad = settled_vad(&VadConfig::default(), &mut state);
+
+        let onset = frames(ONSET_SECS);
+        for _ in 1..onset {
+            assert!(!vad.process(&loud_frame()));
+        }
+        assert!(vad.process(&loud_frame()));
+    }
+
+    #[test]
+    fn speech_ends_after_hangover() {
+        let config = VadConfig {
+            hangover_secs: Some(0.5),
+            ..Default::default()
+        };
+        let mut state = 0x2545_f491;
+        let mut vad = settled_vad(&config, &mut state);
+
+        for _ in 0..frames(0.5) {
+            vad.process(&loud_frame());
+        }
+        assert!(vad.process(&loud_frame()));
+
+        let hangover = frames(0.5);
+        for _ in 1..hangover {
+            assert!(vad.process(&quiet_frame(&mut state)));
+        }
+        assert!(!vad.process(&quiet_frame(&mut state)));
+    }
+
+    #[test]
+    fn short_click_is_ignored() {
+        let mut state = 0x2545_f491;
+        let mut vad = settled_vad(&VadConfig::default(), &mut state);
+
+        for _ in 1..frames(ONSET_SECS) {
+            assert!(!vad.process(&loud_frame()));
+        }
+        for _ in 0..frames(1.0) {
+            assert!(!vad.process(&quiet_frame(&mut state)));
+        }
+    }
+
+    #[test]
+    fn effective_clamps_sensitivity() {
+        let sensitivity = |sensitivity: Option<f32>| {
+            VadConfig {
+                sensitivity,
+                ..Default::default()
+            }
+            .effective()
+            .sensitivity
+        };
+
+        assert_eq!(sensitivity(Some(2.0)), Some(1.0));
+        assert_eq!(sensitivity(Some(-1.0)), Some(0.0));
+        assert_eq!(sensitivity(Some(0.25)), Some(0.25));
+        assert_eq!(sensitivity(Some(f32::NAN)), Some(DEFAULT_SENSITIVITY));
+        assert_eq!(sensitivity(None), Some(DEFAULT_SENSITIVITY));
+    }
+}